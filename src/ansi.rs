@@ -0,0 +1,204 @@
+/// Splits terminal output into text and the control sequences found in it.
+///
+/// CSI (`ESC [ <params> <final>`) and OSC (`ESC ] <data>`, terminated with BEL
+/// or ST, i.e. `ESC \`) sequences are reported as a whole, other escape
+/// sequences are skipped. Sequences longer than the given limit are skipped
+/// too.
+pub struct Scanner {
+    state: State,
+    sequence: String,
+    max_len: usize,
+    overflow: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    /// Character outside of escape sequences, including control characters.
+    Char(char),
+    /// CSI sequence parameters and the final character.
+    Csi(String, char),
+    /// OSC sequence data and the terminator it ended with.
+    Osc(String, &'static str),
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Text,
+    Escape,
+    Csi,
+    Osc,
+    OscEscape,
+}
+
+impl Scanner {
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            state: State::Text,
+            sequence: String::new(),
+            max_len,
+            overflow: false,
+        }
+    }
+
+    pub fn feed(&mut self, c: char) -> Option<Token> {
+        match self.state {
+            State::Text => {
+                if c == '\x1b' {
+                    self.state = State::Escape;
+
+                    None
+                } else {
+                    Some(Token::Char(c))
+                }
+            }
+
+            State::Escape => {
+                self.sequence.clear();
+                self.overflow = false;
+
+                self.state = match c {
+                    '[' => State::Csi,
+                    ']' => State::Osc,
+                    '\x1b' => State::Escape,
+                    _ => State::Text,
+                };
+
+                None
+            }
+
+            State::Csi => match c {
+                '\x20'..='\x3f' => {
+                    self.push(c);
+
+                    None
+                }
+
+                '\x40'..='\x7e' => {
+                    self.state = State::Text;
+
+                    self.take().map(|params| Token::Csi(params, c))
+                }
+
+                '\x1b' => {
+                    self.state = State::Escape;
+
+                    None
+                }
+
+                // C0 controls are executed in the middle of a sequence
+                '\x00'..='\x1f' => None,
+
+                _ => {
+                    self.state = State::Text;
+
+                    None
+                }
+            },
+
+            State::Osc => match c {
+                '\x07' => {
+                    self.state = State::Text;
+
+                    self.take().map(|data| Token::Osc(data, "\x07"))
+                }
+
+                '\x1b' => {
+                    self.state = State::OscEscape;
+
+                    None
+                }
+
+                c => {
+                    self.push(c);
+
+                    None
+                }
+            },
+
+            State::OscEscape => {
+                if c == '\\' {
+                    self.state = State::Text;
+
+                    self.take().map(|data| Token::Osc(data, "\x1b\\"))
+                } else {
+                    // unterminated OSC followed by another escape sequence
+                    self.state = State::Escape;
+
+                    self.feed(c)
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, c: char) {
+        if self.sequence.len() < self.max_len {
+            self.sequence.push(c);
+        } else {
+            self.overflow = true;
+        }
+    }
+
+    fn take(&mut self) -> Option<String> {
+        let sequence = std::mem::take(&mut self.sequence);
+
+        (!self.overflow).then_some(sequence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Scanner, Token};
+
+    fn scan(max_len: usize, chunks: &[&str]) -> Vec<Token> {
+        let mut scanner = Scanner::new(max_len);
+
+        chunks
+            .iter()
+            .flat_map(|chunk| chunk.chars())
+            .filter_map(|c| scanner.feed(c))
+            .collect()
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens = scan(
+            64,
+            &[
+                "a\x07\x1b[1;3",
+                "1mb\x1b7\x1b]0;title\x07\x1b]8;;",
+                "http://a\x1b\\\x1b[\r6n",
+            ],
+        );
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Char('a'),
+                Token::Char('\x07'),
+                Token::Csi("1;31".to_owned(), 'm'),
+                Token::Char('b'),
+                Token::Osc("0;title".to_owned(), "\x07"),
+                Token::Osc("8;;http://a".to_owned(), "\x1b\\"),
+                Token::Csi("6".to_owned(), 'n'),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_osc() {
+        let tokens = scan(64, &["\x1b]0;title\x1b[2Jx"]);
+
+        assert_eq!(
+            tokens,
+            vec![Token::Csi("2".to_owned(), 'J'), Token::Char('x')]
+        );
+    }
+
+    #[test]
+    fn too_long() {
+        let tokens = scan(4, &["\x1b]0;title\x07\x1b[12345m\x1b]0;a\x07"]);
+
+        assert_eq!(tokens, vec![Token::Osc("0;a".to_owned(), "\x07")]);
+    }
+}
@@ -6,16 +6,37 @@ mod v3;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fs;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Read, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
 use std::time::Duration;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
 
+use crate::command_markers::CommandMarkers;
+use crate::compression::{self, Compression};
+use crate::title_bell::{Notice, TitleBell};
 use crate::tty::TtyTheme;
 pub use v2::V2Encoder;
 pub use v3::V3Encoder;
 
+/// Event code of window title changes, set by the recorded program with OSC 0/2.
+pub const TITLE_EVENT_CODE: char = 't';
+
+/// Event code of bells rung by the recorded program.
+pub const BELL_EVENT_CODE: char = 'b';
+
+/// Event code of exit details, written right before the exit event when
+/// enabled, with `ExitDetails` as JSON in its data.
+pub const EXIT_DETAILS_EVENT_CODE: char = 'e';
+
+/// How often a followed file is checked for new data once its end is reached.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 pub struct Asciicast<'a> {
     pub version: Version,
     pub header: Header,
@@ -42,6 +63,14 @@ pub struct Header {
     pub env: Option<HashMap<String, String>>,
 }
 
+impl Header {
+    /// Returns the idle time limit, ignoring an invalid one, e.g. negative.
+    pub fn get_idle_time_limit(&self) -> Option<Duration> {
+        self.idle_time_limit
+            .and_then(|limit| Duration::try_from_secs_f64(limit).ok())
+    }
+}
+
 pub struct Event {
     pub time: Duration,
     pub data: EventData,
@@ -56,6 +85,24 @@ pub enum EventData {
     Other(char, String),
 }
 
+/// How the recorded command ended and the resources it used, stored in the
+/// data of an exit details event, e.g. `[1.5, "e", "{\"signal\":\"SIGSEGV\",...}"]`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExitDetails {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<String>,
+    pub core_dumped: bool,
+    /// Times in seconds.
+    pub wall_time: f64,
+    pub user_time: f64,
+    pub system_time: f64,
+    /// Peak resident set size in kilobytes.
+    pub max_rss: u64,
+}
+
 pub trait Encoder {
     fn header(&mut self, header: &Header) -> Vec<u8>;
     fn event(&mut self, event: &Event) -> Vec<u8>;
@@ -125,8 +172,68 @@ pub fn open_from_path<S: AsRef<Path>>(path: S) -> Result<Asciicast<'static>> {
         .map_err(|e| anyhow!("can't open {}: {}", path.as_ref().to_string_lossy(), e))
 }
 
+/// Opens a file which may still be written to, e.g. by `asciinema rec`.
+///
+/// Reading doesn't stop at the end of the file, but waits for more lines to
+/// be appended, unless the last event read is an exit event.
+pub fn follow_from_path<S: AsRef<Path>>(path: S) -> Result<Asciicast<'static>> {
+    let exited = Arc::new(AtomicBool::new(false));
+
+    let Asciicast {
+        version,
+        header,
+        events,
+    } = fs::File::open(&path)
+        .map(|file| FollowReader {
+            file,
+            exited: exited.clone(),
+        })
+        .map(io::BufReader::new)
+        .map_err(|e| anyhow!(e))
+        .and_then(open)
+        .map_err(|e| anyhow!("can't open {}: {}", path.as_ref().to_string_lossy(), e))?;
+
+    let events = events.inspect(move |event| {
+        let exit = matches!(
+            event,
+            Ok(Event {
+                data: EventData::Exit(_),
+                ..
+            })
+        );
+        exited.store(exit, Ordering::Relaxed);
+    });
+
+    Ok(Asciicast {
+        version,
+        header,
+        events: Box::new(events),
+    })
+}
+
+/// File reader which, instead of returning EOF, polls the file until more
+/// data is written to it. Only after an exit event the end is reported.
+struct FollowReader {
+    file: fs::File,
+    exited: Arc<AtomicBool>,
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.file.read(buf)?;
+
+            if n > 0 || self.exited.load(Ordering::Relaxed) {
+                return Ok(n);
+            }
+
+            thread::sleep(FOLLOW_POLL_INTERVAL);
+        }
+    }
+}
+
 pub fn open<'a, R: BufRead + Send + 'a>(reader: R) -> Result<Asciicast<'a>> {
-    let mut lines = reader.lines();
+    let mut lines = compression::decoder(reader)?.lines();
     let first_line = lines.next().ok_or(anyhow!("empty file"))??;
 
     if let Ok(parser) = v3::open(&first_line) {
@@ -151,6 +258,72 @@ pub fn get_duration<S: AsRef<Path>>(path: S) -> Result<Duration> {
     Ok(time)
 }
 
+/// Returns the duration of a recording as seen during playback, both in the
+/// original timeline and with the idle time limit from the header, or the
+/// given override, applied.
+pub fn get_playback_duration<S: AsRef<Path>>(
+    path: S,
+    idle_time_limit_override: Option<Duration>,
+) -> Result<(Duration, Duration)> {
+    let Asciicast { header, events, .. } = open_from_path(path)?;
+
+    let idle_time_limit = idle_time_limit_override
+        .or(header.get_idle_time_limit())
+        .map_or(f64::MAX, |limit| limit.as_secs_f64());
+
+    let mut time = Duration::ZERO;
+
+    let events = events.inspect(|event| {
+        if let Ok(event) = event {
+            time = event.time;
+        }
+    });
+
+    let limited_time = limit_idle_time(events, idle_time_limit)
+        .last()
+        .map_or(Ok(Duration::ZERO), |e| e.map(|e| e.time))?;
+
+    Ok((time, limited_time))
+}
+
+/// Sets the title in the header of an existing asciicast v2 or v3 file.
+pub fn update_title<S: AsRef<Path>>(path: S, title: &str) -> Result<()> {
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+    let compression = Compression::detect(&data);
+    let mut content = String::new();
+    compression::decoder(&data[..])?.read_to_string(&mut content)?;
+    let (header_line, rest) = content.split_once('\n').unwrap_or((&content, ""));
+
+    let Asciicast {
+        version,
+        mut header,
+        ..
+    } = open(header_line.as_bytes())?;
+
+    let Some(mut encoder) = encoder(version) else {
+        bail!("asciicast v{version} files are not supported");
+    };
+
+    header.title = Some(title.to_owned());
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty());
+    let mut data = encoder.header(&header);
+    data.extend_from_slice(rest.as_bytes());
+
+    if let Some(compression) = compression {
+        let mut writer = compression.writer(Vec::new())?;
+        writer.write_all(&data)?;
+        data = writer.finish()?;
+    }
+
+    let mut file = NamedTempFile::new_in(dir.unwrap_or(Path::new(".")))?;
+    file.write_all(&data)?;
+    fs::set_permissions(file.path(), fs::metadata(path)?.permissions())?;
+    file.persist(path)?;
+
+    Ok(())
+}
+
 impl Event {
     pub fn output(time: Duration, text: String) -> Self {
         Event {
@@ -180,19 +353,43 @@ impl Event {
         }
     }
 
+    pub fn title(time: Duration, title: String) -> Self {
+        Event {
+            time,
+            data: EventData::Other(TITLE_EVENT_CODE, title),
+        }
+    }
+
+    pub fn bell(time: Duration) -> Self {
+        Event {
+            time,
+            data: EventData::Other(BELL_EVENT_CODE, String::new()),
+        }
+    }
+
     pub fn exit(time: Duration, status: i32) -> Self {
         Event {
             time,
             data: EventData::Exit(status),
         }
     }
+
+    pub fn exit_details(time: Duration, details: &ExitDetails) -> Self {
+        Event {
+            time,
+            data: EventData::Other(
+                EXIT_DETAILS_EVENT_CODE,
+                serde_json::to_string(details).unwrap(),
+            ),
+        }
+    }
 }
 
 pub fn limit_idle_time(
     events: impl Iterator<Item = Result<Event>> + Send,
     limit: f64,
 ) -> impl Iterator<Item = Result<Event>> + Send {
-    let limit = Duration::from_micros((limit * 1_000_000.0) as u64);
+    let limit = Duration::try_from_secs_f64(limit).unwrap_or(Duration::MAX);
     let mut prev_time = Duration::from_micros(0);
     let mut offset = Duration::from_micros(0);
 
@@ -212,19 +409,146 @@ pub fn limit_idle_time(
     })
 }
 
-pub fn accelerate(
+pub fn add_title_bell_events(
     events: impl Iterator<Item = Result<Event>> + Send,
-    speed: f64,
 ) -> impl Iterator<Item = Result<Event>> + Send {
-    events.map(move |event| {
-        event.map(|event| {
-            let time = event.time.div_f64(speed);
+    let mut title_bell = TitleBell::new();
 
-            Event { time, ..event }
-        })
+    events.flat_map(move |event| match event {
+        Ok(Event {
+            time,
+            data: EventData::Output(text),
+        }) => {
+            let notices = title_bell.feed(&text);
+            let mut events = vec![Ok(Event::output(time, text))];
+
+            events.extend(notices.into_iter().map(|notice| match notice {
+                Notice::Title(title) => Ok(Event::title(time, title)),
+                Notice::Bell => Ok(Event::bell(time)),
+            }));
+
+            events
+        }
+
+        // drop events added during recording, they're found again in the output
+        Ok(Event {
+            data: EventData::Other(TITLE_EVENT_CODE | BELL_EVENT_CODE, _),
+            ..
+        }) => vec![],
+
+        event => vec![event],
     })
 }
 
+pub fn add_command_markers(
+    events: impl Iterator<Item = Result<Event>> + Send,
+) -> impl Iterator<Item = Result<Event>> + Send {
+    let mut markers = CommandMarkers::new();
+    let mut added: Vec<(Duration, String)> = Vec::new();
+
+    events.flat_map(move |event| match event {
+        Ok(Event {
+            time,
+            data: EventData::Output(text),
+        }) => {
+            let commands = markers.commands();
+            let labels = markers.feed(&text);
+
+            // markers of the previous commands won't show up anymore
+            if markers.commands() > commands {
+                added.clear();
+            }
+
+            added.extend(labels.iter().map(|label| (time, label.clone())));
+            let mut events = vec![Ok(Event::output(time, text))];
+            events.extend(
+                labels
+                    .into_iter()
+                    .map(|label| Ok(Event::marker(time, label))),
+            );
+
+            events
+        }
+
+        // drop markers already added for the same command during recording
+        Ok(Event {
+            time,
+            data: EventData::Marker(label),
+        }) => match added.iter().position(|(t, l)| *t == time && *l == label) {
+            Some(i) => {
+                added.remove(i);
+
+                vec![]
+            }
+
+            None => vec![Ok(Event::marker(time, label))],
+        },
+
+        event => vec![event],
+    })
+}
+
+#[derive(Default)]
+pub struct Repair {
+    pub events: Vec<Event>,
+    pub dropped_lines: usize,
+    pub fixed_times: usize,
+    pub exit_added: bool,
+}
+
+/// Exit status of the exit event added to recordings which lack one.
+pub const REPAIR_EXIT_STATUS: i32 = 1;
+
+/// Salvages events of a recording that was cut short, e.g. by a crash.
+///
+/// Lines which can't be parsed, such as a partially written last line, are
+/// dropped, events going back in time are moved forward, and an exit event is
+/// added if the recording doesn't end with one.
+pub fn repair(events: impl Iterator<Item = Result<Event>>) -> Repair {
+    let mut repair = Repair::default();
+    let mut prev_time = Duration::from_micros(0);
+
+    for event in events {
+        match event {
+            Ok(mut event) => {
+                if event.time < prev_time {
+                    event.time = prev_time;
+                    repair.fixed_times += 1;
+                }
+
+                prev_time = event.time;
+                repair.events.push(event);
+            }
+
+            Err(e) => {
+                repair.dropped_lines += 1;
+
+                // nothing more can be read after a read error, e.g. truncated compressed data
+                if e.downcast_ref::<io::Error>().is_some() {
+                    break;
+                }
+            }
+        }
+    }
+
+    // v2 parser reads exit events as unknown 'x' ones
+    if !matches!(
+        repair.events.last(),
+        Some(Event {
+            data: EventData::Exit(_) | EventData::Other('x', _),
+            ..
+        })
+    ) {
+        repair
+            .events
+            .push(Event::exit(prev_time, REPAIR_EXIT_STATUS));
+
+        repair.exit_added = true;
+    }
+
+    repair
+}
+
 pub fn encoder(version: Version) -> Option<Box<dyn Encoder>> {
     match version {
         Version::One => None,
@@ -241,7 +565,7 @@ mod tests {
     use anyhow::Result;
     use rgb::RGB8;
 
-    use super::{Asciicast, Event, EventData, Header, V2Encoder};
+    use super::{Asciicast, Encoder, Event, EventData, ExitDetails, Header, V2Encoder, V3Encoder};
     use crate::tty::TtyTheme;
 
     #[test]
@@ -475,6 +799,57 @@ mod tests {
         assert_eq!(lines[5][2], "żółć");
     }
 
+    #[test]
+    fn exit_details() {
+        let details = ExitDetails {
+            signal: Some("SIGSEGV".to_owned()),
+            core_dumped: true,
+            wall_time: 1.5,
+            user_time: 0.25,
+            system_time: 0.125,
+            max_rss: 2048,
+            ..Default::default()
+        };
+
+        let encoders: [Box<dyn Encoder>; 2] = [
+            Box::new(V2Encoder::new(Duration::from_micros(0))),
+            Box::new(V3Encoder::new()),
+        ];
+
+        for mut enc in encoders {
+            let mut data = enc.header(&Header::default());
+            data.extend(enc.event(&Event::exit_details(Duration::from_micros(1), &details)));
+            data.extend(enc.event(&Event::exit(Duration::from_micros(1), 139)));
+
+            let lines = parse(data.clone());
+
+            assert_eq!(lines[1].as_array().unwrap().len(), 3);
+            assert_eq!(lines[1][1], "e");
+            assert_eq!(lines[2].as_array().unwrap().len(), 3);
+            assert_eq!(lines[2][1], "x");
+            assert_eq!(lines[2][2], "139");
+
+            let parsed: ExitDetails = serde_json::from_str(lines[1][2].as_str().unwrap()).unwrap();
+            assert_eq!(parsed, details);
+            assert!(!lines[1][2].as_str().unwrap().contains("code"));
+        }
+
+        // the exit event is still a plain one for the parser
+        let mut enc = V3Encoder::new();
+        let mut data = enc.header(&Header::default());
+        data.extend(enc.event(&Event::exit_details(Duration::from_micros(1), &details)));
+        data.extend(enc.event(&Event::exit(Duration::from_micros(1), 139)));
+
+        let events = super::open(&data[..])
+            .unwrap()
+            .events
+            .collect::<Result<Vec<Event>>>()
+            .unwrap();
+
+        assert!(matches!(events[0].data, EventData::Other('e', _)));
+        assert!(matches!(events[1].data, EventData::Exit(139)));
+    }
+
     #[test]
     fn header_encoding() {
         let mut enc = V2Encoder::new(Duration::from_micros(0));
@@ -533,6 +908,70 @@ mod tests {
         assert_eq!(lines[0]["theme"]["palette"], "#000000:#0a0b0c:#141516:#1e1f20:#28292a:#323334:#3c3d3e:#464748:#505152:#5a5b5c:#646566:#6e6f70:#78797a:#828384:#8c8d8e:#969798");
     }
 
+    #[test]
+    fn update_title() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("demo.cast");
+        std::fs::copy("tests/casts/minimal-v3.cast", &path).unwrap();
+
+        super::update_title(&path, "New title").unwrap();
+
+        let Asciicast {
+            version,
+            header,
+            events,
+        } = super::open_from_path(&path).unwrap();
+
+        let events = events.collect::<Result<Vec<Event>>>().unwrap();
+
+        assert_eq!(version, 3);
+        assert_eq!(header.title.as_deref(), Some("New title"));
+        assert_eq!((header.term_cols, header.term_rows), (100, 50));
+        assert!(matches!(events[0].data, EventData::Output(ref s) if s == "hello"));
+
+        let path = dir.path().join("demo.json");
+        std::fs::copy("tests/casts/minimal-v1.json", &path).unwrap();
+
+        assert!(super::update_title(&path, "New title").is_err());
+    }
+
+    #[test]
+    fn follow_from_path() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("demo.cast");
+
+        std::fs::write(
+            &path,
+            "{\"version\": 3, \"term\": {\"cols\": 80, \"rows\": 24}}\n[0.1, \"o\", \"foo\"]\n",
+        )
+        .unwrap();
+
+        let Asciicast { events, .. } = super::follow_from_path(&path).unwrap();
+
+        let writer = {
+            let path = path.clone();
+
+            std::thread::spawn(move || {
+                let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+
+                std::thread::sleep(Duration::from_millis(200));
+                file.write_all(b"[0.1, \"o\", \"b").unwrap();
+                std::thread::sleep(Duration::from_millis(200));
+                file.write_all(b"ar\"]\n[0.1, \"x\", \"0\"]\n").unwrap();
+            })
+        };
+
+        let events = events.collect::<Result<Vec<Event>>>().unwrap();
+        writer.join().unwrap();
+
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0].data, EventData::Output(ref s) if s == "foo"));
+        assert!(matches!(events[1].data, EventData::Output(ref s) if s == "bar"));
+        assert!(matches!(events[2].data, EventData::Exit(0)));
+    }
+
     fn parse(json: Vec<u8>) -> Vec<serde_json::Value> {
         String::from_utf8(json)
             .unwrap()
@@ -543,22 +982,6 @@ mod tests {
             .unwrap()
     }
 
-    #[test]
-    fn accelerate() {
-        let events = [(0u64, "foo"), (20, "bar"), (50, "baz")].map(|(time, output)| {
-            Ok(Event::output(
-                Duration::from_micros(time),
-                output.to_owned(),
-            ))
-        });
-
-        let output = output(super::accelerate(events.into_iter(), 2.0));
-
-        assert_eq!(output[0], (Duration::from_micros(0), "foo".to_owned()));
-        assert_eq!(output[1], (Duration::from_micros(10), "bar".to_owned()));
-        assert_eq!(output[2], (Duration::from_micros(25), "baz".to_owned()));
-    }
-
     #[test]
     fn limit_idle_time() {
         let events = [
@@ -596,6 +1019,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn get_playback_duration() {
+        let path = "tests/casts/full-v2.cast";
+
+        assert_eq!(
+            super::get_playback_duration(path, None).unwrap(),
+            (Duration::from_millis(10_500), Duration::from_millis(10_500))
+        );
+
+        assert_eq!(
+            super::get_playback_duration(path, Some(Duration::from_secs(2))).unwrap(),
+            (Duration::from_millis(10_500), Duration::from_millis(6_300))
+        );
+    }
+
     fn output(events: impl Iterator<Item = Result<Event>>) -> Vec<(Duration, String)> {
         events
             .filter_map(|r| {
@@ -611,4 +1049,122 @@ mod tests {
             })
             .collect::<Vec<_>>()
     }
+
+    #[test]
+    fn add_command_markers() {
+        let events = [
+            (0, "$ \x1b]133;B\x07"),
+            (1_000_000, "make\r\n\x1b]133;C\x07"),
+            (2_000_000, "done\r\n\x1b]133;D;0\x07"),
+        ]
+        .map(|(time, output)| {
+            Ok(Event::output(
+                Duration::from_micros(time),
+                output.to_owned(),
+            ))
+        });
+
+        let events = super::add_command_markers(events.into_iter())
+            .map(|event| event.unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(events.len(), 5);
+        assert!(matches!(&events[2].data, EventData::Marker(label) if label == "make"));
+        assert_eq!(events[2].time, Duration::from_micros(1_000_000));
+        assert!(
+            matches!(&events[4].data, EventData::Marker(label) if label == "make (exit status 0)")
+        );
+        assert_eq!(events[4].time, Duration::from_micros(2_000_000));
+
+        // already marked recording
+        let events = super::add_command_markers(events.into_iter().map(Ok));
+
+        assert_eq!(events.count(), 5);
+
+        // already marked recording, with markers following other output
+        let events = [
+            Event::output(Duration::from_micros(0), "$ \x1b]133;B\x07".to_owned()),
+            Event::output(Duration::from_micros(1), "ls\r\n\x1b]133;C\x07".to_owned()),
+            Event::output(Duration::from_micros(1), "foo\r\n".to_owned()),
+            Event::marker(Duration::from_micros(1), "ls".to_owned()),
+            Event::output(Duration::from_micros(2), "\x1b]133;D;0\x07".to_owned()),
+            Event::input(Duration::from_micros(3), "p".to_owned()),
+            Event::output(Duration::from_micros(3), "$ \x1b]133;B\x07".to_owned()),
+            Event::marker(Duration::from_micros(2), "ls (exit status 0)".to_owned()),
+        ];
+
+        let labels = super::add_command_markers(events.into_iter().map(Ok))
+            .filter_map(|event| match event.unwrap().data {
+                EventData::Marker(label) => Some(label),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(labels, ["ls", "ls (exit status 0)"]);
+    }
+
+    #[test]
+    fn add_title_bell_events() {
+        let events = [
+            (0, "\x1b]2;vim\x07"),
+            (1_000_000, "oops\x07"),
+            (2_000_000, "\x1b]0;\x1b\\"),
+        ]
+        .map(|(time, output)| {
+            Ok(Event::output(
+                Duration::from_micros(time),
+                output.to_owned(),
+            ))
+        });
+
+        let events = super::add_title_bell_events(events.into_iter())
+            .map(|event| event.unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(events.len(), 6);
+        assert!(matches!(&events[1].data, EventData::Other('t', title) if title == "vim"));
+        assert!(matches!(&events[3].data, EventData::Other('b', data) if data.is_empty()));
+        assert_eq!(events[3].time, Duration::from_micros(1_000_000));
+        assert!(matches!(&events[5].data, EventData::Other('t', title) if title.is_empty()));
+
+        // recording with title and bell events
+        let events = super::add_title_bell_events(events.into_iter().map(Ok));
+
+        assert_eq!(events.count(), 6);
+    }
+
+    #[test]
+    fn repair() {
+        let data = "{\"version\": 2, \"width\": 80, \"height\": 24}\n\
+            [1.0, \"o\", \"foo\"]\n\
+            [3.0, \"o\", \"bar\"]\n\
+            [2.5, \"o\", \"baz\"]\n\
+            [4.0, \"o\", \"qu";
+
+        let cast = super::open(data.as_bytes()).unwrap();
+        let repair = super::repair(cast.events);
+        let times = repair
+            .events
+            .iter()
+            .map(|e| e.time.as_secs_f64())
+            .collect::<Vec<_>>();
+
+        assert_eq!(repair.dropped_lines, 1);
+        assert_eq!(repair.fixed_times, 1);
+        assert!(repair.exit_added);
+        assert_eq!(times, vec![1.0, 3.0, 3.0, 3.0]);
+        assert!(matches!(repair.events[2].data, EventData::Output(ref s) if s == "baz"));
+        assert!(matches!(repair.events[3].data, EventData::Exit(1)));
+
+        // intact recording, missing only the exit event
+        let cast = super::open_from_path("tests/casts/full-v3.cast").unwrap();
+        let repair = super::repair(cast.events);
+        let last = repair.events.last().unwrap();
+
+        assert_eq!(repair.dropped_lines, 0);
+        assert_eq!(repair.fixed_times, 0);
+        assert!(repair.exit_added);
+        assert!(matches!(last.data, EventData::Exit(1)));
+        assert_eq!(last.time, Duration::from_micros(13_400_002));
+    }
 }
@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
 use std::num::ParseIntError;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 
@@ -23,6 +24,17 @@ pub struct Cli {
         long_help
     )]
     pub quiet: bool,
+
+    /// Use the settings from the named profile of the config file, i.e. the [profiles.NAME] table. A profile can override any key of the session, playback and server sections, while command line options still take precedence over it.
+    #[clap(
+        long,
+        global = true,
+        display_order = 102,
+        value_name = "NAME",
+        help = "Use settings from the named config profile",
+        long_help
+    )]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -34,7 +46,7 @@ pub enum Commands {
     /// Press <ctrl+d> or type 'exit' to end the recording session.
     /// Press <ctrl+\> to pause/resume capture of the session.
     ///
-    /// During the session, the ASCIINEMA_SESSION environment variable is set to a unique session ID.
+    /// During the session, the ASCIINEMA_SESSION environment variable is set to a unique session ID, and ASCIINEMA_CTL_SOCKET is set to the path of the session's control socket (see the ctl command).
     #[clap(
         visible_alias = "rec",
         about = "Record a terminal session",
@@ -47,6 +59,9 @@ pub enum Commands {
   asciinema rec --command \"python script.py\" demo.cast
       Records execution of a Python script
 
+  asciinema rec demo.cast -- python \"my script.py\" --verbose
+      Records execution of a program with arguments passed as is, without a shell
+
   asciinema rec --idle-time-limit 2 demo.cast
       Records with idle time capped at 2 seconds
 
@@ -57,7 +72,10 @@ pub enum Commands {
       Continues recording to an existing file
 
   asciinema rec demo.txt
-      Records as a plain-text log - output format inferred from the .txt extension"
+      Records as a plain-text log - output format inferred from the .txt extension
+
+  asciinema rec --script demo.yaml demo.cast
+      Records a session driven by a script, without any keyboard interaction"
     )]
     Record(Record),
 
@@ -68,7 +86,7 @@ pub enum Commands {
     /// Press <ctrl+d> or type 'exit' to end the streaming session.
     /// Press <ctrl+\> to pause/resume capture of the session.
     ///
-    /// During the session, the ASCIINEMA_SESSION environment variable is set to a unique session ID.
+    /// During the session, the ASCIINEMA_SESSION environment variable is set to a unique session ID, and ASCIINEMA_CTL_SOCKET is set to the path of the session's control socket (see the ctl command).
     #[clap(
         about = "Stream a terminal session",
         long_about,
@@ -101,7 +119,7 @@ pub enum Commands {
     /// Press <ctrl+d> or type 'exit' to end the session.
     /// Press <ctrl+\> to pause/resume capture of the session.
     ///
-    /// During the session, the ASCIINEMA_SESSION environment variable is set to a unique session ID.
+    /// During the session, the ASCIINEMA_SESSION environment variable is set to a unique session ID, and ASCIINEMA_CTL_SOCKET is set to the path of the session's control socket (see the ctl command).
     #[clap(
         about = "Record and stream a terminal session",
         long_about,
@@ -134,7 +152,11 @@ pub enum Commands {
     /// Press <ctrl+c> to interrupt the playback.
     /// Press <space> to pause/resume.
     /// Press '.' to step forward (while paused).
-    /// Press ']' to skip to the next marker (while paused).
+    /// Press ']' to skip to the next marker, '[' to go back to the previous one.
+    /// Press <left>/<right> to seek backward/forward by 5 seconds, or by 30 seconds with <shift>.
+    /// Press '+'/'-' to double/halve the playback speed, '=' to go back to the initial speed.
+    /// Press 'i' to turn the idle time limit off/on.
+    /// Press 's' to show/hide the status line with the position, current marker and speed (in the window title).
     #[clap(
         about = "Play back a terminal session",
         long_about,
@@ -153,7 +175,13 @@ pub enum Commands {
       Plays back directly from a URL
 
   asciinema play --pause-on-markers demo.cast
-      Plays back, pausing automatically at every marker"
+      Plays back, pausing automatically at every marker
+
+  asciinema play --from-marker deploy --end-at 12:30 demo.cast
+      Plays back from the deploy marker until the 12:30 mark
+
+  asciinema play --follow /shared/session.cast
+      Plays back a recording which is still in progress from its current end, waiting for new events"
     )]
     Play(Play),
 
@@ -215,6 +243,46 @@ pub enum Commands {
       Downloads a remote recording and converts it to the latest asciicast format (v3)"
     )]
     Convert(Convert),
+
+    /// Repair a recording that was cut short.
+    ///
+    /// Fixes an asciicast file left incomplete by a crash during recording, so that it can be played and uploaded again. The partially written last line is dropped, events going back in time are moved forward, and an exit event is added if the recording doesn't end with one. Compressed files stay compressed.
+    #[clap(
+        about = "Repair a recording that was cut short",
+        long_about,
+        after_help = "\x1b[1;4mExamples\x1b[0m:
+
+  asciinema repair demo.cast
+      Repairs the recording in place
+
+  asciinema repair demo.cast -o fixed.cast
+      Saves the repaired recording to a new file, leaving the original intact"
+    )]
+    Repair(Repair),
+
+    /// Control a running session.
+    ///
+    /// Sends a command to a running record, stream or session command via its control socket. This allows adding markers, pausing/resuming capture, changing the title, or querying the status of a session from scripts and other processes, including headless sessions.
+    ///
+    /// When executed inside a session, the control socket is found via the ASCIINEMA_CTL_SOCKET environment variable.
+    #[clap(
+        about = "Control a running session",
+        long_about,
+        after_help = "\x1b[1;4mExamples\x1b[0m:
+
+  asciinema ctl marker \"tests started\"
+      Adds a labelled marker to the current session
+
+  asciinema ctl pause
+      Pauses capture of the current session
+
+  asciinema ctl title \"Deploying to production\"
+      Changes the title of the current session
+
+  asciinema ctl --socket /run/user/1000/asciinema/<ID>.sock status
+      Prints the status of a session running elsewhere"
+    )]
+    Ctl(Ctl),
 }
 
 #[derive(Debug, Args)]
@@ -222,7 +290,7 @@ pub struct Record {
     /// Output file path
     pub file: String,
 
-    /// Specify the format for the output file. The default is asciicast-v3. If the file path ends with .txt, the txt format will be selected automatically unless --output-format is explicitly specified.
+    /// Specify the format for the output file. The default is asciicast-v3. If the file path ends with .txt or .raw, the txt or raw format will be selected automatically unless --output-format is explicitly specified. If the file path ends with .gz or .zst, e.g. demo.cast.gz, the file is compressed with gzip or zstd respectively.
     #[arg(
         short = 'f',
         long,
@@ -242,7 +310,17 @@ pub struct Record {
     )]
     pub command: Option<String>,
 
-    /// Enable recording of keyboard input in addition to terminal output. When enabled, both what you type and what appears on the screen will be captured. Note that sensitive input like passwords will also be recorded when this option is enabled. Can also be set via the config file option session.capture_input.
+    /// Command to start in the session, given as a program with its arguments after `--`, for example: -- python "my script.py". Unlike with --command, the arguments are passed to the program verbatim, without an intermediate shell.
+    #[arg(
+        last = true,
+        value_name = "ARGS",
+        conflicts_with = "command",
+        help = "Command with arguments to start, without a shell",
+        long_help
+    )]
+    pub args: Vec<String>,
+
+    /// Enable recording of keyboard input in addition to terminal output. When enabled, both what you type and what appears on the screen will be captured. Input typed while the terminal echo is turned off, e.g. at password prompts of sudo or ssh, is not recorded. Can also be set via the config file option session.capture_input.
     #[arg(
         long,
         short = 'I',
@@ -252,7 +330,7 @@ pub struct Record {
     )]
     pub capture_input: bool,
 
-    /// Specify which environment variables to capture and include in the recording metadata. This helps ensure the recording context is preserved, e.g., for auditing. Provide a comma-separated list of variable names, for example: --rec-env "USER,SHELL,TERM". If not specified, only the SHELL variable is captured by default. Can also be set via the config file option session.capture_env.
+    /// Specify which environment variables to capture and include in the recording metadata. This helps ensure the recording context is preserved, e.g., for auditing. Provide a comma-separated list of variable names, for example: --rec-env "USER,SHELL,TERM". Names may contain * and ? wildcards (e.g. "LC_*,GIT_*"), and patterns prefixed with ! exclude matching variables from the ones selected by the other patterns (e.g. "GIT_*,!*TOKEN*"). If not specified, or if only exclusions are given, only the SHELL variable is captured. Values of variables matching the config file option session.capture_env_redact are replaced with ***. Can also be set via the config file option session.capture_env.
     #[arg(
         long,
         value_name = "VARS",
@@ -261,6 +339,78 @@ pub struct Record {
     )]
     pub capture_env: Option<String>,
 
+    /// Start the command in the specified working directory instead of the current one. Can also be set via the config file option session.cwd.
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Working directory for the command",
+        long_help
+    )]
+    pub cwd: Option<PathBuf>,
+
+    /// Set an environment variable for the command, for example: --env LANG=C.UTF-8. Can be used multiple times. Can also be set via the config file option session.env.
+    #[arg(
+        long = "env",
+        value_name = "KEY=VALUE",
+        value_parser = validate_env_var,
+        help = "Set environment variable for the command",
+        long_help
+    )]
+    pub set_env: Vec<String>,
+
+    /// Remove an environment variable inherited by the command. Can be used multiple times. Can also be set via the config file option session.unset_env.
+    #[arg(
+        long,
+        value_name = "KEY",
+        help = "Remove environment variable for the command",
+        long_help
+    )]
+    pub unset_env: Vec<String>,
+
+    /// Start the command with an empty environment instead of inheriting the environment of asciinema. Only TERM, the variables set by asciinema itself (such as ASCIINEMA_SESSION) and the ones set with --env are passed to the command. Useful for reproducible recordings. Can also be set via the config file option session.clean_env.
+    #[arg(long, help = "Don't inherit environment variables", long_help)]
+    pub clean_env: bool,
+
+    /// Set the file mode creation mask of the command, given as an octal number, for example: --umask 022. Can also be set via the config file option session.umask.
+    #[arg(
+        long,
+        value_name = "MASK",
+        value_parser = parse_umask,
+        help = "File mode creation mask for the command",
+        long_help
+    )]
+    pub umask: Option<u32>,
+
+    /// Stop the session after the given number of seconds of recording time (not counting pauses). The command is terminated, a marker explaining the reason is added, and asciinema exits with status 124.
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_duration,
+        help = "Stop the session after the given duration",
+        long_help
+    )]
+    pub max_duration: Option<Duration>,
+
+    /// Stop the session once the given amount of terminal output (and input, when captured) has been recorded. Accepts a number of bytes with an optional K, M or G suffix, e.g. 500M. The command is terminated, a marker explaining the reason is added, and asciinema exits with status 124.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        help = "Stop the session after recording the given amount of data",
+        long_help
+    )]
+    pub max_size: Option<u64>,
+
+    /// Stop the session when there's no terminal activity (output or keyboard input) for the given number of seconds. The command is terminated, a marker explaining the reason is added, and asciinema exits with status 124.
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_duration,
+        help = "Stop the session after the given idle time",
+        long_help
+    )]
+    pub stop_after_idle: Option<Duration>,
+
     /// Append the new session to an existing recording file instead of creating a new one. This allows you to continue a previous recording session. The timing will be adjusted to maintain continuity from where the previous recording ended. Cannot be used together with --overwrite.
     #[arg(short, long, help = "Append to an existing recording file", long_help)]
     pub append: bool,
@@ -274,6 +424,28 @@ pub struct Record {
     )]
     pub overwrite: bool,
 
+    /// Split the recording into multiple files (segments), starting a new one after the given number of seconds. The output file path can contain strftime-style placeholders, e.g. %Y%m%d-%H%M%S, which are expanded using the start time of each segment, and {n}, which is replaced with the segment number. If the path doesn't contain {n}, -{n} is inserted before the file extension, so segments started within the same second don't overwrite each other. Each segment is a complete recording that can be played on its own. Cannot be used together with --append.
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_duration,
+        conflicts_with = "append",
+        help = "Start a new output file after the given duration",
+        long_help
+    )]
+    pub segment_duration: Option<Duration>,
+
+    /// Split the recording into multiple files (segments), starting a new one once the current file reaches the given size. Accepts a number of bytes with an optional K, M or G suffix, e.g. 100M. See --segment-duration for details on segment file naming. Cannot be used together with --append.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        conflicts_with = "append",
+        help = "Start a new output file after reaching the given size",
+        long_help
+    )]
+    pub segment_size: Option<u64>,
+
     /// Set a descriptive title that will be stored in the recording metadata. This title may be displayed by players and is useful for organizing and identifying recordings. For example: --title "Installing Podman on Ubuntu".
     #[arg(short, long, help = "Title of the recording", long_help)]
     pub title: Option<String>,
@@ -288,7 +460,7 @@ pub struct Record {
     )]
     pub idle_time_limit: Option<f64>,
 
-    /// Record in headless mode without using the terminal for input/output. This is useful for automated or scripted recordings where you don't want asciinema to interfere with the current terminal session. The recorded command will still execute normally, but asciinema won't display its output in your terminal. Headless mode is enabled automatically when running in an environment where a terminal is not available.
+    /// Record in headless mode without using the terminal for input/output. This is useful for automated or scripted recordings where you don't want asciinema to interfere with the current terminal session. The recorded command will still execute normally, but asciinema won't display its output in your terminal. Headless mode is enabled automatically when running in an environment where a terminal is not available. Queries sent by the command to the terminal, such as cursor position, device attributes, window size or colors, are answered by an emulated terminal, with colors taken from the session.headless_theme config option.
     #[arg(
         long,
         help = "Headless mode - don't use the terminal for I/O",
@@ -296,10 +468,31 @@ pub struct Record {
     )]
     pub headless: bool,
 
-    /// Override the terminal window size used for the recording session. Specify dimensions as COLSxROWS (e.g., 80x24 for 80 columns by 24 rows). You can specify just columns (80x) or just rows (x24) to override only one dimension. This is useful for ensuring consistent recording dimensions regardless of your current terminal size.
+    /// Override the terminal window size used for the recording session. Specify dimensions as COLSxROWS (e.g., 80x24 for 80 columns by 24 rows). You can specify just columns (80x) or just rows (x24) to override only one dimension. This is useful for ensuring consistent recording dimensions regardless of your current terminal size. Can also be set via the config file option session.window_size.
     #[arg(long, value_name = "COLSxROWS", value_parser = parse_window_size, help = "Override session's terminal window size", long_help)]
     pub window_size: Option<(Option<u16>, Option<u16>)>,
 
+    /// Drive the session with a script instead of keyboard input, for fully automated, reproducible recordings. The script is a YAML file with a list of steps, each being one of: type (text typed with a human-like delay between keystrokes), press (a key, e.g. enter, tab, up, ^c), wait_for (a regular expression to wait for in the output, optionally with a timeout in seconds), sleep (seconds), marker (a label) and resize (COLSxROWS). Optional top-level settings are size (initial COLSxROWS), typing_delay (seconds, default 0.08), timeout (for wait_for, default 30) and seed (for the typing delay variation). Implies --headless. The session is stopped when a step fails, e.g. when waiting for output times out.
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Drive the session with a YAML script",
+        long_help
+    )]
+    pub script: Option<PathBuf>,
+
+    /// Upload the recording to an asciinema server when the session ends, and print the URL of the uploaded recording. The local file is kept. Only asciicast recordings which are neither compressed nor segmented can be uploaded. Can also be enabled via the config file option session.auto_upload.
+    #[arg(long, help = "Upload the recording when the session ends", long_help)]
+    pub upload: bool,
+
+    /// Upload the recording to an asciinema server when the session ends, like --upload, then delete the local file once the server has confirmed the upload. The file is kept when the upload fails.
+    #[arg(
+        long,
+        help = "Upload the recording when the session ends and delete the local file",
+        long_help
+    )]
+    pub upload_and_delete: bool,
+
     /// Make the asciinema command exit with the same status code as the recorded session. By default, asciinema exits with status 0 regardless of what happens in the recorded session. With this option, if the recorded command exits with a non-zero status, asciinema will also exit with the same status.
     #[arg(long, help = "Return the session's exit status", long_help)]
     pub return_: bool,
@@ -342,12 +535,13 @@ pub struct Play {
         short,
         long,
         value_name = "SECS",
+        value_parser = parse_duration,
         help = "Limit idle time to a given number of seconds",
         long_help
     )]
-    pub idle_time_limit: Option<f64>,
+    pub idle_time_limit: Option<Duration>,
 
-    /// Automatically pause playback when encountering marker events. Markers are special events that can be added during recording to mark important points in a session. When this option is enabled, playback will pause at each marker, allowing you to control the flow of the demonstration. Use <space> to resume, '.' to step through events, or ']' to skip to the next marker.
+    /// Automatically pause playback when encountering marker events. Markers are special events that can be added during recording to mark important points in a session. When this option is enabled, playback will pause at each marker, allowing you to control the flow of the demonstration. Use <space> to resume, '.' to step through events, or ']' and '[' to skip to the next or previous marker.
     #[arg(short = 'm', long, help = "Automatically pause on markers", long_help)]
     pub pause_on_markers: bool,
 
@@ -359,6 +553,55 @@ pub struct Play {
         long_help
     )]
     pub resize: bool,
+
+    /// Start the playback at the given time, skipping everything before it. The screen is rebuilt to show its state at that point. The time is given in seconds or as [HH:]MM:SS, with minutes and seconds below 60, e.g. 90, 1:30 or 1:05:00, and refers to the timeline of the player, i.e. with the idle time limit applied.
+    #[arg(
+        long,
+        value_name = "TIME",
+        value_parser = parse_time,
+        conflicts_with = "from_marker",
+        help = "Start the playback at the given time",
+        long_help
+    )]
+    pub start_at: Option<Duration>,
+
+    /// End the playback at the given time. The time is given in seconds or as [HH:]MM:SS, with minutes and seconds below 60, e.g. 90, 1:30 or 1:05:00, and refers to the timeline of the player, i.e. with the idle time limit applied.
+    #[arg(
+        long,
+        value_name = "TIME",
+        value_parser = parse_time,
+        conflicts_with = "to_marker",
+        help = "End the playback at the given time",
+        long_help
+    )]
+    pub end_at: Option<Duration>,
+
+    /// Start the playback at the first marker with the given label, skipping everything before it. The screen is rebuilt to show its state at that point.
+    #[arg(
+        long,
+        value_name = "LABEL",
+        help = "Start the playback at the given marker",
+        long_help
+    )]
+    pub from_marker: Option<String>,
+
+    /// End the playback at the first marker with the given label which follows the starting point.
+    #[arg(
+        long,
+        value_name = "LABEL",
+        help = "End the playback at the given marker",
+        long_help
+    )]
+    pub to_marker: Option<String>,
+
+    /// Keep playing the file as it grows, like tail -f. The playback starts at the current end of the file, showing the screen as it is at that point, unless --start-at or --from-marker is given, and the earlier part can still be seeked back to. After that, the player waits for new events to be appended to it and plays them as they arrive, until the file ends with an exit event, i.e. the recorded session has finished. This allows watching a session which is still being recorded, e.g. with asciinema rec on a shared filesystem, without streaming it. Files containing several sessions added with asciinema rec --append are played through, as only an exit event at the end of the file stops the playback. Press <ctrl+c> to stop following a file which doesn't get an exit event.
+    #[arg(
+        short = 'f',
+        long,
+        help = "Keep playing new events appended to the file",
+        long_help
+    )]
+    pub follow: bool,
 }
 
 #[derive(Debug, Args)]
@@ -381,11 +624,21 @@ pub struct Stream {
     )]
     pub command: Option<String>,
 
-    /// Enable recording of keyboard input in addition to terminal output. When enabled, both what you type and what appears on the screen will be captured. Note that sensitive input like passwords will also be recorded when this option is enabled. If the server has stream recording enabled then keyboard input will be included in the recording file created on the server side. Can also be set via the config file option session.capture_input.
+    /// Command to start in the session, given as a program with its arguments after `--`, for example: -- python "my script.py". Unlike with --command, the arguments are passed to the program verbatim, without an intermediate shell.
+    #[arg(
+        last = true,
+        value_name = "ARGS",
+        conflicts_with = "command",
+        help = "Command with arguments to start, without a shell",
+        long_help
+    )]
+    pub args: Vec<String>,
+
+    /// Enable recording of keyboard input in addition to terminal output. When enabled, both what you type and what appears on the screen will be captured. Input typed while the terminal echo is turned off, e.g. at password prompts of sudo or ssh, is not recorded. If the server has stream recording enabled then keyboard input will be included in the recording file created on the server side. Can also be set via the config file option session.capture_input.
     #[arg(long, short = 'I', help = "Enable input (keyboard) capture", long_help)]
     pub capture_input: bool,
 
-    /// Specify which environment variables to capture and include in the stream metadata. Provide a comma-separated list of variable names, for example: --rec-env "USER,SHELL,TERM". If not specified, only the SHELL variable is captured by default. If the server has stream recording enabled then these environment variables will be included in the recording file created on the server side. Can also be set via the config file option session.capture_env.
+    /// Specify which environment variables to capture and include in the stream metadata. Provide a comma-separated list of variable names, for example: --rec-env "USER,SHELL,TERM". Names may contain * and ? wildcards (e.g. "LC_*,GIT_*"), and patterns prefixed with ! exclude matching variables from the ones selected by the other patterns (e.g. "GIT_*,!*TOKEN*"). If not specified, or if only exclusions are given, only the SHELL variable is captured. Values of variables matching the config file option session.capture_env_redact are replaced with ***. If the server has stream recording enabled then these environment variables will be included in the recording file created on the server side. Can also be set via the config file option session.capture_env.
     #[arg(
         long,
         value_name = "VARS",
@@ -394,11 +647,83 @@ pub struct Stream {
     )]
     pub capture_env: Option<String>,
 
+    /// Start the command in the specified working directory instead of the current one. Can also be set via the config file option session.cwd.
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Working directory for the command",
+        long_help
+    )]
+    pub cwd: Option<PathBuf>,
+
+    /// Set an environment variable for the command, for example: --env LANG=C.UTF-8. Can be used multiple times. Can also be set via the config file option session.env.
+    #[arg(
+        long = "env",
+        value_name = "KEY=VALUE",
+        value_parser = validate_env_var,
+        help = "Set environment variable for the command",
+        long_help
+    )]
+    pub set_env: Vec<String>,
+
+    /// Remove an environment variable inherited by the command. Can be used multiple times. Can also be set via the config file option session.unset_env.
+    #[arg(
+        long,
+        value_name = "KEY",
+        help = "Remove environment variable for the command",
+        long_help
+    )]
+    pub unset_env: Vec<String>,
+
+    /// Start the command with an empty environment instead of inheriting the environment of asciinema. Only TERM, the variables set by asciinema itself (such as ASCIINEMA_SESSION) and the ones set with --env are passed to the command. Useful for reproducible recordings. Can also be set via the config file option session.clean_env.
+    #[arg(long, help = "Don't inherit environment variables", long_help)]
+    pub clean_env: bool,
+
+    /// Set the file mode creation mask of the command, given as an octal number, for example: --umask 022. Can also be set via the config file option session.umask.
+    #[arg(
+        long,
+        value_name = "MASK",
+        value_parser = parse_umask,
+        help = "File mode creation mask for the command",
+        long_help
+    )]
+    pub umask: Option<u32>,
+
+    /// Stop the session after the given number of seconds of recording time (not counting pauses). The command is terminated, a marker explaining the reason is added, and asciinema exits with status 124.
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_duration,
+        help = "Stop the session after the given duration",
+        long_help
+    )]
+    pub max_duration: Option<Duration>,
+
+    /// Stop the session once the given amount of terminal output (and input, when captured) has been recorded. Accepts a number of bytes with an optional K, M or G suffix, e.g. 500M. The command is terminated, a marker explaining the reason is added, and asciinema exits with status 124.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        help = "Stop the session after recording the given amount of data",
+        long_help
+    )]
+    pub max_size: Option<u64>,
+
+    /// Stop the session when there's no terminal activity (output or keyboard input) for the given number of seconds. The command is terminated, a marker explaining the reason is added, and asciinema exits with status 124.
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_duration,
+        help = "Stop the session after the given idle time",
+        long_help
+    )]
+    pub stop_after_idle: Option<Duration>,
+
     /// Set a descriptive title for the streaming session. This title is displayed to viewers (when doing remote streaming with --remote). For example: --title "Building a REST API". If the server has stream recording enabled then the title will be included in the recording file created on the server side.
     #[arg(short, long, help = "Title of the session", long_help)]
     pub title: Option<String>,
 
-    /// Stream in headless mode without using the terminal for input/output. This is useful for automated or scripted streaming where you don't want asciinema to interfere with the current terminal session. The streamed command will still execute normally and be visible to viewers, but won't be displayed in your local terminal. Headless mode is enabled automatically when running in an environment where a terminal is not available.
+    /// Stream in headless mode without using the terminal for input/output. This is useful for automated or scripted streaming where you don't want asciinema to interfere with the current terminal session. The streamed command will still execute normally and be visible to viewers, but won't be displayed in your local terminal. Headless mode is enabled automatically when running in an environment where a terminal is not available. Queries sent by the command to the terminal, such as cursor position, device attributes, window size or colors, are answered by an emulated terminal, with colors taken from the session.headless_theme config option.
     #[arg(
         long,
         help = "Headless mode - don't use the terminal for I/O",
@@ -406,7 +731,7 @@ pub struct Stream {
     )]
     pub headless: bool,
 
-    /// Override the terminal window size used for the streaming session. Specify dimensions as COLSxROWS (e.g., 80x24 for 80 columns by 24 rows). You can specify just columns (80x) or just rows (x24) to override only one dimension. This is useful for ensuring consistent streaming dimensions regardless of your current terminal size.
+    /// Override the terminal window size used for the streaming session. Specify dimensions as COLSxROWS (e.g., 80x24 for 80 columns by 24 rows). You can specify just columns (80x) or just rows (x24) to override only one dimension. This is useful for ensuring consistent streaming dimensions regardless of your current terminal size. Can also be set via the config file option session.window_size.
     #[arg(long, value_name = "COLSxROWS", value_parser = parse_window_size, help = "Override session's terminal window size", long_help)]
     pub window_size: Option<(Option<u16>, Option<u16>)>,
 
@@ -426,17 +751,18 @@ pub struct Stream {
 #[derive(Debug, Args)]
 #[clap(group(ArgGroup::new("mode").args(&["output_file", "stream_local", "stream_remote"]).multiple(true).required(true)))]
 pub struct Session {
-    /// Save the session to a file at the specified path. Can be combined with local and remote streaming.
+    /// Save the session to a file at the specified path. Can be used multiple times to save the session to several files at once, e.g. -o demo.cast -o demo.txt. The format of an individual file can be chosen by appending it to the path after a colon, e.g. -o demo.log:raw. Can be combined with local and remote streaming.
     #[arg(
         short,
         long,
-        value_name = "PATH",
-        help = "Save the session to a file",
+        value_name = "PATH[:FORMAT]",
+        value_parser = parse_output_file,
+        help = "Save the session to a file (can be repeated)",
         long_help
     )]
-    pub output_file: Option<String>,
+    pub output_file: Vec<OutputFile>,
 
-    /// Specify the format for the output file when saving is enabled with --output-file. The default is asciicast-v3. If the output file path ends with .txt, the txt format will be selected automatically unless this option is explicitly specified.
+    /// Specify the format for the output files saved with --output-file, unless given with the PATH:FORMAT syntax. The default is asciicast-v3. If the output file path ends with .txt or .raw, the txt or raw format will be selected automatically unless this option is explicitly specified. If the output file path ends with .gz or .zst, e.g. demo.cast.gz, the file is compressed with gzip or zstd respectively.
     #[arg(
         short = 'f',
         long,
@@ -464,11 +790,11 @@ pub struct Session {
     )]
     pub command: Option<String>,
 
-    /// Enable recording of keyboard input in addition to terminal output. When enabled, both what you type and what appears on the screen will be captured. Note that sensitive input like passwords will also be recorded when this option is enabled. If the server has stream recording enabled then keyboard input will be included in the recording file created on the server side. Can also be set via the config file option session.capture_input.
+    /// Enable recording of keyboard input in addition to terminal output. When enabled, both what you type and what appears on the screen will be captured. Input typed while the terminal echo is turned off, e.g. at password prompts of sudo or ssh, is not recorded. If the server has stream recording enabled then keyboard input will be included in the recording file created on the server side. Can also be set via the config file option session.capture_input.
     #[arg(long, short = 'I', help = "Enable input (keyboard) capture", long_help)]
     pub capture_input: bool,
 
-    /// Specify which environment variables to capture and include in the session metadata. Provide a comma-separated list of variable names, for example: --rec-env "USER,SHELL,TERM". If not specified, only the SHELL variable is captured by default. If the server has stream recording enabled then these environment variables will be included in the recording file created on the server side. Can also be set via the config file option session.capture_env.
+    /// Specify which environment variables to capture and include in the session metadata. Provide a comma-separated list of variable names, for example: --rec-env "USER,SHELL,TERM". Names may contain * and ? wildcards (e.g. "LC_*,GIT_*"), and patterns prefixed with ! exclude matching variables from the ones selected by the other patterns (e.g. "GIT_*,!*TOKEN*"). If not specified, or if only exclusions are given, only the SHELL variable is captured. Values of variables matching the config file option session.capture_env_redact are replaced with ***. If the server has stream recording enabled then these environment variables will be included in the recording file created on the server side. Can also be set via the config file option session.capture_env.
     #[arg(
         long,
         value_name = "VARS",
@@ -477,6 +803,78 @@ pub struct Session {
     )]
     pub capture_env: Option<String>,
 
+    /// Start the command in the specified working directory instead of the current one. Can also be set via the config file option session.cwd.
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Working directory for the command",
+        long_help
+    )]
+    pub cwd: Option<PathBuf>,
+
+    /// Set an environment variable for the command, for example: --env LANG=C.UTF-8. Can be used multiple times. Can also be set via the config file option session.env.
+    #[arg(
+        long = "env",
+        value_name = "KEY=VALUE",
+        value_parser = validate_env_var,
+        help = "Set environment variable for the command",
+        long_help
+    )]
+    pub set_env: Vec<String>,
+
+    /// Remove an environment variable inherited by the command. Can be used multiple times. Can also be set via the config file option session.unset_env.
+    #[arg(
+        long,
+        value_name = "KEY",
+        help = "Remove environment variable for the command",
+        long_help
+    )]
+    pub unset_env: Vec<String>,
+
+    /// Start the command with an empty environment instead of inheriting the environment of asciinema. Only TERM, the variables set by asciinema itself (such as ASCIINEMA_SESSION) and the ones set with --env are passed to the command. Useful for reproducible recordings. Can also be set via the config file option session.clean_env.
+    #[arg(long, help = "Don't inherit environment variables", long_help)]
+    pub clean_env: bool,
+
+    /// Set the file mode creation mask of the command, given as an octal number, for example: --umask 022. Can also be set via the config file option session.umask.
+    #[arg(
+        long,
+        value_name = "MASK",
+        value_parser = parse_umask,
+        help = "File mode creation mask for the command",
+        long_help
+    )]
+    pub umask: Option<u32>,
+
+    /// Stop the session after the given number of seconds of recording time (not counting pauses). The command is terminated, a marker explaining the reason is added, and asciinema exits with status 124.
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_duration,
+        help = "Stop the session after the given duration",
+        long_help
+    )]
+    pub max_duration: Option<Duration>,
+
+    /// Stop the session once the given amount of terminal output (and input, when captured) has been recorded. Accepts a number of bytes with an optional K, M or G suffix, e.g. 500M. The command is terminated, a marker explaining the reason is added, and asciinema exits with status 124.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        help = "Stop the session after recording the given amount of data",
+        long_help
+    )]
+    pub max_size: Option<u64>,
+
+    /// Stop the session when there's no terminal activity (output or keyboard input) for the given number of seconds. The command is terminated, a marker explaining the reason is added, and asciinema exits with status 124.
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_duration,
+        help = "Stop the session after the given idle time",
+        long_help
+    )]
+    pub stop_after_idle: Option<Duration>,
+
     /// Append the new session to an existing recording file instead of creating a new one. This allows you to continue a previous recording session. The timing will be adjusted to maintain continuity from where the previous recording ended. Cannot be used together with --overwrite. Only applies when --output-file is specified.
     #[arg(short, long, help = "Append to an existing recording file", long_help)]
     pub append: bool,
@@ -490,6 +888,28 @@ pub struct Session {
     )]
     pub overwrite: bool,
 
+    /// Split the recording into multiple files (segments), starting a new one after the given number of seconds. The output file path can contain strftime-style placeholders, e.g. %Y%m%d-%H%M%S, which are expanded using the start time of each segment, and {n}, which is replaced with the segment number. If the path doesn't contain {n}, -{n} is inserted before the file extension, so segments started within the same second don't overwrite each other. Each segment is a complete recording that can be played on its own. Cannot be used together with --append.
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_duration,
+        conflicts_with = "append",
+        help = "Start a new output file after the given duration",
+        long_help
+    )]
+    pub segment_duration: Option<Duration>,
+
+    /// Split the recording into multiple files (segments), starting a new one once the current file reaches the given size. Accepts a number of bytes with an optional K, M or G suffix, e.g. 100M. See --segment-duration for details on segment file naming. Cannot be used together with --append.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        conflicts_with = "append",
+        help = "Start a new output file after reaching the given size",
+        long_help
+    )]
+    pub segment_size: Option<u64>,
+
     /// Set a descriptive title for the session that will be stored in the recording metadata and displayed to stream viewers (when doing remote streaming with --remote). For example: --title "Installing Podman on Ubuntu". If the server has stream recording enabled then the title will be included in the recording file created on the server side.
     #[arg(short, long, help = "Title of the session", long_help)]
     pub title: Option<String>,
@@ -504,7 +924,7 @@ pub struct Session {
     )]
     pub idle_time_limit: Option<f64>,
 
-    /// Run the session in headless mode without using the terminal for input/output. This is useful for automated or scripted sessions where you don't want asciinema to interfere with the current terminal session. The session command will still execute normally and be recorded/streamed, but won't be displayed in your local terminal. Headless mode is enabled automatically when running in an environment where a terminal is not available.
+    /// Run the session in headless mode without using the terminal for input/output. This is useful for automated or scripted sessions where you don't want asciinema to interfere with the current terminal session. The session command will still execute normally and be recorded/streamed, but won't be displayed in your local terminal. Headless mode is enabled automatically when running in an environment where a terminal is not available. Queries sent by the command to the terminal, such as cursor position, device attributes, window size or colors, are answered by an emulated terminal, with colors taken from the session.headless_theme config option.
     #[arg(
         long,
         help = "Headless mode - don't use the terminal for I/O",
@@ -512,10 +932,31 @@ pub struct Session {
     )]
     pub headless: bool,
 
-    /// Override the terminal window size used for the session. Specify dimensions as COLSxROWS (e.g., 80x24 for 80 columns by 24 rows). You can specify just columns (80x) or just rows (x24) to override only one dimension. This is useful for ensuring consistent recording dimensions regardless of your current terminal size.
+    /// Override the terminal window size used for the session. Specify dimensions as COLSxROWS (e.g., 80x24 for 80 columns by 24 rows). You can specify just columns (80x) or just rows (x24) to override only one dimension. This is useful for ensuring consistent recording dimensions regardless of your current terminal size. Can also be set via the config file option session.window_size.
     #[arg(long, value_name = "COLSxROWS", value_parser = parse_window_size, help = "Override session's terminal window size", long_help)]
     pub window_size: Option<(Option<u16>, Option<u16>)>,
 
+    /// Drive the session with a script instead of keyboard input, for fully automated, reproducible recordings. The script is a YAML file with a list of steps, each being one of: type (text typed with a human-like delay between keystrokes), press (a key, e.g. enter, tab, up, ^c), wait_for (a regular expression to wait for in the output, optionally with a timeout in seconds), sleep (seconds), marker (a label) and resize (COLSxROWS). Optional top-level settings are size (initial COLSxROWS), typing_delay (seconds, default 0.08), timeout (for wait_for, default 30) and seed (for the typing delay variation). Implies --headless. The session is stopped when a step fails, e.g. when waiting for output times out.
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Drive the session with a YAML script",
+        long_help
+    )]
+    pub script: Option<PathBuf>,
+
+    /// Upload the recording to an asciinema server when the session ends, and print the URL of the uploaded recording. The local file is kept. Only asciicast recordings which are neither compressed nor segmented can be uploaded. Can also be enabled via the config file option session.auto_upload.
+    #[arg(long, help = "Upload the recording when the session ends", long_help)]
+    pub upload: bool,
+
+    /// Upload the recording to an asciinema server when the session ends, like --upload, then delete the local file once the server has confirmed the upload. The file is kept when the upload fails.
+    #[arg(
+        long,
+        help = "Upload the recording when the session ends and delete the local file",
+        long_help
+    )]
+    pub upload_and_delete: bool,
+
     /// Make the asciinema command exit with the same status code as the session command. By default, asciinema exits with status 0 regardless of what happens in the session. With this option, if the session command exits with a non-zero status, asciinema will also exit with that same status.
     #[arg(long, help = "Return the session's exit status", long_help)]
     pub return_: bool,
@@ -530,6 +971,16 @@ pub struct Session {
 
     #[arg(hide = true)]
     pub env: Vec<String>,
+
+    /// Command to start in the session, given as a program with its arguments after `--`, for example: -- python "my script.py". Unlike with --command, the arguments are passed to the program verbatim, without an intermediate shell.
+    #[arg(
+        last = true,
+        value_name = "ARGS",
+        conflicts_with = "command",
+        help = "Command with arguments to start, without a shell",
+        long_help
+    )]
+    pub args: Vec<String>,
 }
 
 #[derive(Debug, Args)]
@@ -541,10 +992,10 @@ pub struct Cat {
 
 #[derive(Debug, Args)]
 pub struct Convert {
-    /// The source recording to convert. Can be a local file path, HTTP(S) URL for remote files, or '-' to read from standard input. Remote URLs allow converting recordings directly from the web without need for manual downloading. Supported input formats include asciicast v1, v2 and v3.
+    /// The source recording to convert. Can be a local file path, HTTP(S) URL for remote files, or '-' to read from standard input. Remote URLs allow converting recordings directly from the web without need for manual downloading. Supported input formats include asciicast v1, v2 and v3, optionally compressed with gzip or zstd.
     pub input: String,
 
-    /// The output path for the converted recording. Can be a file path or '-' to write to standard output.
+    /// The output path for the converted recording. Can be a file path or '-' to write to standard output. If the file path ends with .gz or .zst, the output is compressed with gzip or zstd respectively.
     pub output: String,
 
     /// Specify the format for the converted recording. The default is asciicast-v3. If the output file path ends with .txt, the txt format will be selected automatically unless this option is explicitly specified.
@@ -565,6 +1016,30 @@ pub struct Convert {
         long_help
     )]
     pub overwrite: bool,
+
+    /// Add markers for shell commands found in the recording. Shells with OSC 133 shell integration (prompt/command start and end sequences) let asciinema tell where each command begins and ends. A marker labelled with the command line is added when a command starts, and another one including its exit status when it finishes. Can be enabled during recording via the config file option session.command_markers.
+    #[arg(long, help = "Add markers for shell commands (OSC 133)", long_help)]
+    pub command_markers: bool,
+
+    /// Add title and bell events found in the recording. Window title changes (OSC 0 and OSC 2 sequences) become title events ('t') and bell characters become bell events ('b'), while the raw output is kept as is. Recordings made with this version of asciinema already include these events; use this option for older recordings.
+    #[arg(long, help = "Add title and bell events", long_help)]
+    pub title_bell_events: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct Repair {
+    /// The path to the asciicast recording file to repair, in asciicast v2 or v3 format, optionally compressed with gzip or zstd.
+    pub file: String,
+
+    /// Save the repaired recording to a file at the specified path instead of replacing the original file.
+    #[arg(
+        short,
+        long,
+        value_name = "PATH",
+        help = "Save the repaired recording to a new file",
+        long_help
+    )]
+    pub output: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -584,6 +1059,46 @@ pub struct Auth {
     pub server_url: Option<String>,
 }
 
+#[derive(Debug, Args)]
+pub struct Ctl {
+    #[command(subcommand)]
+    pub command: CtlCommand,
+
+    /// Path of the control socket of the session to control. Defaults to the value of the ASCIINEMA_CTL_SOCKET environment variable, which is set for all processes running inside a session.
+    #[arg(
+        long,
+        value_name = "PATH",
+        global = true,
+        help = "Control socket path [default: $ASCIINEMA_CTL_SOCKET]",
+        long_help
+    )]
+    pub socket: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CtlCommand {
+    /// Add a marker to the session
+    Marker {
+        /// Marker label
+        label: Option<String>,
+    },
+
+    /// Pause capture of the session
+    Pause,
+
+    /// Resume capture of the session
+    Resume,
+
+    /// Change the title of the session
+    Title {
+        /// New title
+        title: String,
+    },
+
+    /// Print the status of the session
+    Status,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
 pub enum Format {
     /// Full-featured session format, with timing and metadata (current generation) - https://docs.asciinema.org/manual/asciicast/v3/
@@ -596,6 +1111,13 @@ pub enum Format {
     Txt,
 }
 
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct OutputFile {
+    pub path: String,
+    pub format: Option<Format>,
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub enum RelayTarget {
@@ -603,7 +1125,7 @@ pub enum RelayTarget {
     WsProducerUrl(url::Url),
 }
 
-fn parse_window_size(s: &str) -> Result<(Option<u16>, Option<u16>), String> {
+pub fn parse_window_size(s: &str) -> Result<(Option<u16>, Option<u16>), String> {
     match s.split_once('x') {
         Some((cols, "")) => {
             let cols: u16 = cols.parse().map_err(|e: ParseIntError| e.to_string())?;
@@ -628,6 +1150,103 @@ fn parse_window_size(s: &str) -> Result<(Option<u16>, Option<u16>), String> {
     }
 }
 
+fn validate_env_var(s: &str) -> Result<String, String> {
+    match s.split_once('=') {
+        Some((name, _)) if !name.is_empty() => Ok(s.to_owned()),
+        _ => Err("must be in KEY=VALUE format".to_owned()),
+    }
+}
+
+fn parse_size(s: &str) -> Result<u64, String> {
+    let (number, multiplier) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&s[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+
+    let number: u64 = number.parse().map_err(|e: ParseIntError| e.to_string())?;
+
+    number
+        .checked_mul(multiplier)
+        .ok_or(format!("size too large: {s}"))
+}
+
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let secs: f64 = s.parse().map_err(|_| format!("invalid duration: {s}"))?;
+
+    Duration::try_from_secs_f64(secs).map_err(|_| format!("invalid duration: {s}"))
+}
+
+fn parse_output_file(s: &str) -> Result<OutputFile, String> {
+    if let Some((path, format)) = s.rsplit_once(':') {
+        if let Ok(format) = Format::from_str(format, true) {
+            if path.is_empty() {
+                return Err("missing file path".to_owned());
+            }
+
+            return Ok(OutputFile {
+                path: path.to_owned(),
+                format: Some(format),
+            });
+        }
+    }
+
+    Ok(OutputFile {
+        path: s.to_owned(),
+        format: None,
+    })
+}
+
+fn parse_time(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid time: {s}");
+
+    let mut fields: Vec<&str> = s.split(':').collect();
+    let secs = fields.pop().unwrap();
+
+    if fields.is_empty() {
+        return parse_duration(s).map_err(|_| invalid());
+    }
+
+    let secs: f64 = secs.parse().map_err(|_| invalid())?;
+
+    if fields.len() > 2 || !(0.0..60.0).contains(&secs) {
+        return Err(invalid());
+    }
+
+    let mut mins: u64 = 0;
+
+    for (i, field) in fields.iter().enumerate() {
+        if field.is_empty() || !field.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        let value: u64 = field.parse().map_err(|_| invalid())?;
+
+        // minutes are limited, hours aren't
+        if i == fields.len() - 1 && value >= 60 {
+            return Err(invalid());
+        }
+
+        mins = mins
+            .checked_mul(60)
+            .and_then(|mins| mins.checked_add(value))
+            .ok_or_else(invalid)?;
+    }
+
+    mins.checked_mul(60)
+        .map(Duration::from_secs)
+        .and_then(|time| time.checked_add(Duration::from_secs_f64(secs)))
+        .ok_or_else(invalid)
+}
+
+pub fn parse_umask(s: &str) -> Result<u32, String> {
+    match u32::from_str_radix(s, 8) {
+        Ok(mask) if mask <= 0o777 => Ok(mask),
+        _ => Err("must be an octal number between 000 and 777".to_owned()),
+    }
+}
+
 fn validate_forward_target(s: &str) -> Result<RelayTarget, String> {
     let s = s.trim();
 
@@ -646,3 +1265,64 @@ fn validate_forward_target(s: &str) -> Result<RelayTarget, String> {
         Err(e) => Err(e.to_string()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[test]
+    fn parse_size() {
+        assert_eq!(super::parse_size("1024"), Ok(1024));
+        assert_eq!(super::parse_size("2k"), Ok(2048));
+        assert_eq!(super::parse_size("500M"), Ok(500 << 20));
+        assert_eq!(super::parse_size("1G"), Ok(1 << 30));
+        assert!(super::parse_size("").is_err());
+        assert!(super::parse_size("-1K").is_err());
+        assert!(super::parse_size("1.5M").is_err());
+        assert!(super::parse_size("18446744073709551615").is_ok());
+        assert!(super::parse_size("18446744073709551615K").is_err());
+        assert!(super::parse_size("17179869184G").is_err());
+    }
+
+    #[test]
+    fn parse_duration() {
+        assert_eq!(super::parse_duration("60"), Ok(Duration::from_secs(60)));
+        assert_eq!(super::parse_duration("0.5"), Ok(Duration::from_millis(500)));
+        assert!(super::parse_duration("-1").is_err());
+        assert!(super::parse_duration("NaN").is_err());
+        assert!(super::parse_duration("inf").is_err());
+        assert!(super::parse_duration("1e30").is_err());
+        assert!(super::parse_duration("1m").is_err());
+    }
+
+    #[test]
+    fn parse_time() {
+        assert_eq!(super::parse_time("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(super::parse_time("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(super::parse_time("1:30"), Ok(Duration::from_secs(90)));
+        assert_eq!(
+            super::parse_time("01:02:03.5"),
+            Ok(Duration::from_millis(3_723_500))
+        );
+        assert_eq!(
+            super::parse_time("100:00:00"),
+            Ok(Duration::from_secs(360_000))
+        );
+        assert!(super::parse_time("1:90").is_err());
+        assert!(super::parse_time("1:60").is_err());
+        assert!(super::parse_time("90:00").is_err());
+        assert!(super::parse_time("1:60:00").is_err());
+        assert!(super::parse_time("1:2:3:4").is_err());
+        assert!(super::parse_time("1.5:00").is_err());
+        assert!(super::parse_time("-1:00").is_err());
+        assert!(super::parse_time("1:-5").is_err());
+        assert!(super::parse_time(":30").is_err());
+        assert!(super::parse_time("-1").is_err());
+        assert!(super::parse_time("NaN").is_err());
+        assert!(super::parse_time("inf").is_err());
+        assert!(super::parse_time("1:inf").is_err());
+        assert!(super::parse_time("1e30").is_err());
+        assert!(super::parse_time("999999999999999999999:00:00").is_err());
+        assert!(super::parse_time("307445734561825860:00:00").is_err());
+    }
+}
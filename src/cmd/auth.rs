@@ -5,8 +5,8 @@ use crate::cli;
 use crate::config::Config;
 
 impl cli::Auth {
-    pub fn run(self) -> Result<()> {
-        let mut config = Config::new(self.server_url.clone())?;
+    pub fn run(self, profile: Option<&str>) -> Result<()> {
+        let mut config = Config::new(self.server_url.clone(), profile)?;
         let server_url = config.get_server_url()?;
         let server_hostname = server_url.host().unwrap();
         let auth_url = api::get_auth_url(&mut config)?;
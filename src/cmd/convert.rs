@@ -6,6 +6,7 @@ use anyhow::{bail, Result};
 
 use crate::asciicast;
 use crate::cli::{self, Format};
+use crate::compression::Compression;
 use crate::encoder::{
     self, AsciicastV2Encoder, AsciicastV3Encoder, EncoderExt, RawEncoder, TextEncoder,
 };
@@ -15,16 +16,39 @@ impl cli::Convert {
     pub fn run(self) -> Result<()> {
         let input_path = self.get_input_path()?;
         let output_path = self.get_output_path();
-        let cast = asciicast::open_from_path(&*input_path)?;
+        let mut cast = asciicast::open_from_path(&*input_path)?;
+
+        if self.command_markers {
+            cast.events = Box::new(asciicast::add_command_markers(cast.events));
+        }
+
+        if self.title_bell_events {
+            cast.events = Box::new(asciicast::add_title_bell_events(cast.events));
+        }
+
         let mut encoder = self.get_encoder();
+        let compression = Compression::from_path(&output_path);
         let mut output_file = self.open_output_file(output_path)?;
 
-        encoder.encode_to_file(cast, &mut output_file)
+        match compression {
+            Some(compression) => {
+                let mut writer = compression.writer(output_file)?;
+                encoder.encode_to_file(cast, &mut writer)?;
+                writer.finish()?;
+
+                Ok(())
+            }
+
+            None => encoder.encode_to_file(cast, &mut output_file),
+        }
     }
 
     fn get_encoder(&self) -> Box<dyn encoder::Encoder> {
         let format = self.output_format.unwrap_or_else(|| {
-            if self.output.to_lowercase().ends_with(".txt") {
+            let output = self.output.to_lowercase();
+            let output = output.trim_end_matches(".gz").trim_end_matches(".zst");
+
+            if output.ends_with(".txt") {
                 Format::Txt
             } else {
                 Format::AsciicastV3
@@ -0,0 +1,55 @@
+use std::env;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use tokio::runtime::Runtime;
+
+use crate::cli::{self, CtlCommand};
+use crate::control::{self, Request};
+
+impl cli::Ctl {
+    pub fn run(self) -> Result<()> {
+        Runtime::new()?.block_on(self.do_run())
+    }
+
+    async fn do_run(self) -> Result<()> {
+        let path = self.get_socket_path()?;
+
+        let request = match self.command {
+            CtlCommand::Marker { label } => Request::Marker {
+                label: label.unwrap_or_default(),
+            },
+
+            CtlCommand::Pause => Request::Pause,
+            CtlCommand::Resume => Request::Resume,
+            CtlCommand::Title { title } => Request::Title { title },
+            CtlCommand::Status => Request::Status,
+        };
+
+        let response = control::request(&path, &request).await?;
+
+        if let Some(status) = response.status {
+            println!("elapsed: {:.3}s", status.elapsed);
+            println!("paused: {}", if status.paused { "yes" } else { "no" });
+
+            if let Some(title) = status.title {
+                println!("title: {title}");
+            }
+
+            for output in status.outputs {
+                println!("output: {output}");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_socket_path(&self) -> Result<PathBuf> {
+        self.socket
+            .clone()
+            .or(env::var_os(control::SOCKET_ENV_VAR).map(PathBuf::from))
+            .ok_or(anyhow!(
+                "not inside an asciinema session, use --socket to specify the control socket"
+            ))
+    }
+}
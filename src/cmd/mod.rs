@@ -1,6 +1,8 @@
 pub mod auth;
 pub mod cat;
 pub mod convert;
+pub mod ctl;
 pub mod play;
+pub mod repair;
 pub mod session;
 pub mod upload;
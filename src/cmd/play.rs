@@ -1,42 +1,47 @@
 use std::path::Path;
+use std::time::Duration;
 
 use tokio::runtime::Runtime;
 
 use crate::asciicast;
 use crate::cli;
 use crate::config::{self, Config};
-use crate::player::{self, KeyBindings};
+use crate::player::{self, Bound, KeyBindings, Options, Range, Reopen};
 use crate::status;
 use crate::util;
 
 impl cli::Play {
-    pub fn run(self) -> anyhow::Result<()> {
-        let config = Config::new(None)?;
-        let speed = self.speed.or(config.playback.speed).unwrap_or(1.0);
-        let idle_time_limit = self.idle_time_limit.or(config.playback.idle_time_limit);
+    pub fn run(self, profile: Option<&str>) -> anyhow::Result<()> {
+        let config = Config::new(None, profile)?;
         let path = self.get_path()?;
-        let keys = get_key_bindings(&config.playback)?;
+        let options = self.get_options(&config.playback, (*path).as_ref())?;
         let runtime = Runtime::new()?;
 
         status::info!("Replaying session from {}", self.file);
 
+        // standard input can't be read again when seeking back
+        let reopen = (self.file != "-").then(|| {
+            let path = (*path).as_ref().to_path_buf();
+            let follow = self.follow;
+
+            Box::new(move || open(&path, follow)) as Box<Reopen>
+        });
+
         let ended = loop {
-            let recording = asciicast::open_from_path(&*path)?;
+            // standard input is read until closed anyway
+            let recording = open((*path).as_ref(), self.follow && self.file != "-")?;
 
-            let ended = runtime.block_on(player::play(
-                recording,
-                speed,
-                idle_time_limit,
-                self.pause_on_markers,
-                &keys,
-                self.resize,
-            ))?;
+            let ended = runtime.block_on(player::play(recording, reopen.as_deref(), &options))?;
 
             if !self.loop_ || !ended {
                 break ended;
             }
         };
 
+        // don't wait for the reader of an interrupted playback, which may
+        // still be waiting for the followed file to grow
+        runtime.shutdown_background();
+
         if ended {
             status::info!("Playback ended");
         } else {
@@ -46,6 +51,69 @@ impl cli::Play {
         Ok(())
     }
 
+    fn get_options(&self, config: &config::Playback, path: &Path) -> anyhow::Result<Options> {
+        let idle_time_limit = self.idle_time_limit.or(config
+            .idle_time_limit
+            .and_then(|limit| Duration::try_from_secs_f64(limit).ok()));
+
+        Ok(Options {
+            speed: self.speed.or(config.speed).unwrap_or(1.0),
+            idle_time_limit,
+            duration: self.get_duration(path, idle_time_limit),
+            pause_on_markers: self.pause_on_markers,
+            auto_resize: self.resize,
+            range: self.get_range(path, idle_time_limit),
+            follow: self.follow,
+            keys: get_key_bindings(config)?,
+        })
+    }
+
+    /// Scans the recording for its total playback time, unknown for standard
+    /// input and for files which are still being written.
+    fn get_duration(
+        &self,
+        path: &Path,
+        idle_time_limit: Option<Duration>,
+    ) -> Option<(Duration, Duration)> {
+        if self.file == "-" || self.follow {
+            return None;
+        }
+
+        asciicast::get_playback_duration(path, idle_time_limit).ok()
+    }
+
+    fn get_range(&self, path: &Path, idle_time_limit: Option<Duration>) -> Range {
+        let start = self
+            .start_at
+            .map(Bound::Time)
+            .or(self.from_marker.clone().map(Bound::Marker))
+            .or_else(|| {
+                self.get_follow_start(path, idle_time_limit)
+                    .map(Bound::Time)
+            });
+
+        let end = self
+            .end_at
+            .map(Bound::Time)
+            .or(self.to_marker.clone().map(Bound::Marker));
+
+        Range { start, end }
+    }
+
+    /// Returns the time of the last event written so far to a followed file,
+    /// for starting the playback there, like tail -f does. When the file
+    /// can't be scanned, e.g. its last line is still being written, the
+    /// playback starts from the beginning.
+    fn get_follow_start(&self, path: &Path, idle_time_limit: Option<Duration>) -> Option<Duration> {
+        if !self.follow || self.file == "-" {
+            return None;
+        }
+
+        asciicast::get_playback_duration(path, idle_time_limit)
+            .ok()
+            .map(|(_, limited_time)| limited_time)
+    }
+
     fn get_path(&self) -> anyhow::Result<Box<dyn AsRef<Path>>> {
         if self.file == "-" {
             Ok(Box::new(std::path::Path::new("/dev/stdin")))
@@ -55,6 +123,14 @@ impl cli::Play {
     }
 }
 
+fn open(path: &Path, follow: bool) -> anyhow::Result<asciicast::Asciicast<'static>> {
+    if follow {
+        asciicast::follow_from_path(path)
+    } else {
+        asciicast::open_from_path(path)
+    }
+}
+
 fn get_key_bindings(config: &config::Playback) -> anyhow::Result<KeyBindings> {
     let mut keys = KeyBindings::default();
 
@@ -70,5 +146,45 @@ fn get_key_bindings(config: &config::Playback) -> anyhow::Result<KeyBindings> {
         keys.next_marker = key;
     }
 
+    if let Some(key) = config.prev_marker_key()? {
+        keys.prev_marker = key;
+    }
+
+    if let Some(key) = config.seek_backward_key()? {
+        keys.seek_backward = key;
+    }
+
+    if let Some(key) = config.seek_forward_key()? {
+        keys.seek_forward = key;
+    }
+
+    if let Some(key) = config.seek_backward_long_key()? {
+        keys.seek_backward_long = key;
+    }
+
+    if let Some(key) = config.seek_forward_long_key()? {
+        keys.seek_forward_long = key;
+    }
+
+    if let Some(key) = config.status_key()? {
+        keys.status = key;
+    }
+
+    if let Some(key) = config.speed_up_key()? {
+        keys.speed_up = key;
+    }
+
+    if let Some(key) = config.slow_down_key()? {
+        keys.slow_down = key;
+    }
+
+    if let Some(key) = config.reset_speed_key()? {
+        keys.reset_speed = key;
+    }
+
+    if let Some(key) = config.idle_time_limit_key()? {
+        keys.idle_time_limit = key;
+    }
+
     Ok(keys)
 }
@@ -0,0 +1,78 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use tempfile::NamedTempFile;
+
+use crate::asciicast::{self, Asciicast};
+use crate::cli;
+use crate::compression::Compression;
+use crate::status;
+
+impl cli::Repair {
+    pub fn run(self) -> Result<()> {
+        let data = fs::read(&self.file).map_err(|e| anyhow!("can't open {}: {e}", self.file))?;
+        let compression = Compression::detect(&data);
+
+        let Asciicast {
+            version,
+            header,
+            events,
+        } = asciicast::open(&data[..]).map_err(|e| anyhow!("can't open {}: {e}", self.file))?;
+
+        let Some(mut encoder) = asciicast::encoder(version) else {
+            bail!("repairing asciicast v{version} files is not supported");
+        };
+
+        let repair = asciicast::repair(events);
+
+        if repair.dropped_lines == 0 && repair.fixed_times == 0 && !repair.exit_added {
+            status::info!("Nothing to repair in {}", self.file);
+
+            if self.output.is_none() {
+                return Ok(());
+            }
+        }
+
+        let mut data = encoder.header(&header);
+
+        for event in &repair.events {
+            data.extend(encoder.event(event));
+        }
+
+        if let Some(compression) = compression {
+            let mut writer = compression.writer(Vec::new())?;
+            writer.write_all(&data)?;
+            data = writer.finish()?;
+        }
+
+        let path = Path::new(self.output.as_ref().unwrap_or(&self.file));
+
+        if self.output.is_some() && path.exists() {
+            bail!("{} already exists", path.to_string_lossy());
+        }
+
+        let dir = path.parent().filter(|p| !p.as_os_str().is_empty());
+        let mut file = NamedTempFile::new_in(dir.unwrap_or(Path::new(".")))?;
+        file.write_all(&data)?;
+        fs::set_permissions(file.path(), fs::metadata(&self.file)?.permissions())?;
+        file.persist(path)?;
+
+        if repair.dropped_lines > 0 {
+            status::info!("Dropped {} damaged line(s)", repair.dropped_lines);
+        }
+
+        if repair.fixed_times > 0 {
+            status::info!("Fixed time of {} event(s)", repair.fixed_times);
+        }
+
+        if repair.exit_added {
+            status::info!("Added missing exit event");
+        }
+
+        status::info!("Repaired recording saved to {}", path.to_string_lossy());
+
+        Ok(())
+    }
+}
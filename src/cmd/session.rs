@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet};
 use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::{self, ExitCode};
 use std::time::{Duration, SystemTime};
@@ -7,37 +8,53 @@ use std::time::{Duration, SystemTime};
 use anyhow::{anyhow, bail, Context, Result};
 use tokio::net::TcpListener;
 use tokio::runtime::Runtime;
+use tokio::sync::{mpsc, watch};
 use tokio::time;
 use tokio_util::sync::CancellationToken;
-use tracing::debug;
 use tracing::level_filters::LevelFilter;
+use tracing::{debug, error};
 use tracing_subscriber::EnvFilter;
 use url::Url;
 
 use crate::api::{self, StreamChangeset, StreamResponse};
 use crate::asciicast::{self, Version};
 use crate::cli::{self, Format, RelayTarget};
+use crate::command_markers::CommandMarkers;
+use crate::compression::{self, Compression};
 use crate::config::{self, Config};
+use crate::control::{self, Controller, Socket};
 use crate::encoder::{AsciicastV2Encoder, AsciicastV3Encoder, Encoder, RawEncoder, TextEncoder};
-use crate::file_writer::FileWriter;
+use crate::file_writer::{FileWriter, Segmentation, SyncPolicy};
 use crate::forwarder;
 use crate::hash;
+use crate::hook::{self, HookEnv};
 use crate::locale;
 use crate::notifier::{self, BackgroundNotifier, Notifier, NullNotifier};
+use crate::pty::ChildOptions;
+use crate::redaction::Redactor;
+use crate::script::Script;
 use crate::server;
-use crate::session::{self, KeyBindings, Metadata, TermInfo};
+use crate::session::{self, KeyBindings, Limits, Metadata, TermInfo};
 use crate::status;
 use crate::stream::Stream;
-use crate::tty::{self, DevTty, FixedSizeTty, NullTty, RawTty};
+use crate::tty::{self, DevTty, EmulatedTty, FixedSizeTty, NullTty, RawTty, TtySize, TtyTheme};
+use crate::util;
+
+/// Exit status used when the session was stopped due to reaching a limit,
+/// same as the one used by timeout(1).
+const LIMIT_EXIT_CODE: u8 = 124;
 
 impl cli::Session {
-    pub fn run(mut self) -> Result<ExitCode> {
+    pub fn run(mut self, profile: Option<&str>) -> Result<ExitCode> {
         locale::check_utf8_locale()?;
         self.init_logging()?;
 
-        let exit_status = Runtime::new()?.block_on(self.do_run())?;
+        let outcome = Runtime::new()?.block_on(self.do_run(profile))?;
+        let exit_status = outcome.status;
 
-        if !self.return_ || exit_status == 0 {
+        if outcome.limit.is_some() {
+            Ok(ExitCode::from(LIMIT_EXIT_CODE))
+        } else if !self.return_ || exit_status == 0 {
             Ok(ExitCode::from(0))
         } else if exit_status > 0 {
             Ok(ExitCode::from(exit_status as u8))
@@ -46,18 +63,35 @@ impl cli::Session {
         }
     }
 
-    async fn do_run(&mut self) -> Result<i32> {
-        let mut config = Config::new(self.server_url.clone())?;
+    async fn do_run(&mut self, profile: Option<&str>) -> Result<session::Outcome> {
+        let mut config = Config::new(self.server_url.clone(), profile)?;
         let command = self.get_command(&config.session);
         let keys = get_key_bindings(&config.session)?;
+        let redactor = get_redactor(&config.redaction)?;
+        let command_markers = config.session.command_markers.then(CommandMarkers::new);
+        let mut child_options = self.get_child_options(&config.session)?;
         let notifier = get_notifier(&config);
-        let (tty, term_info) = probe_tty(self.headless, self.window_size).await?;
+        let script = self.script.as_ref().map(Script::load).transpose()?;
+        let window_size = self.window_size.or(config.session.window_size()?).or(script
+            .as_ref()
+            .and_then(|s| s.size)
+            .map(|TtySize(cols, rows)| (Some(cols), Some(rows))));
+        let headless_theme = config.session.headless_theme()?;
+        let (tty, term_info) = probe_tty(
+            self.headless || script.is_some(),
+            window_size,
+            headless_theme,
+        )
+        .await?;
         let metadata = self.get_session_metadata(&config.session, term_info)?;
-        let file_writer = self.get_file_writer(&metadata, notifier.clone()).await?;
+        let file_writers = self
+            .get_file_writers(&metadata, &config.session, notifier.clone())
+            .await?;
         let listener = self.get_listener().await?;
         let relay = self.get_relay(&metadata, &mut config).await?;
         let relay_id = relay.as_ref().map(|r| r.id());
         let parent_session_relay_id = get_parent_session_relay_id();
+        let session_id = get_session_id();
 
         if relay_id.is_some()
             && parent_session_relay_id.is_some()
@@ -70,10 +104,23 @@ impl cli::Session {
             }
         }
 
+        let mut hook_env = HookEnv {
+            session_id: session_id.clone(),
+            output_paths: file_writers
+                .iter()
+                .map(|(path, _, _)| self.display_path(path))
+                .collect(),
+            stream_url: get_stream_url(listener.as_ref(), relay.as_ref()),
+            upload_urls: Vec::new(),
+            exit_status: None,
+        };
+
+        run_hook("pre_hook", config.session.pre_hook.as_ref(), &hook_env).await;
+
         status::info!("asciinema session started");
 
-        if let Some(path) = self.output_file.as_ref() {
-            status::info!("Recording to {}", path);
+        for (path, _, _) in &file_writers {
+            status::info!("Recording to {}", self.display_path(path));
         }
 
         if let Some(listener) = &listener {
@@ -91,13 +138,35 @@ impl cli::Session {
             status::info!("Press <ctrl+d> or type 'exit' to end");
         }
 
+        let output_names = self.get_output_names(listener.as_ref(), relay.as_ref());
         let stream = Stream::new();
         let shutdown_token = CancellationToken::new();
+        let (commands_tx, commands_rx) = mpsc::channel(16);
+        let (title_tx, title_rx) = watch::channel(metadata.title.clone());
+        let socket = get_control_socket(&session_id);
+        let socket_path = socket.as_ref().map(|s| s.path().to_owned());
+
+        let controller = socket.map(|socket| {
+            let controller = Controller::new(commands_tx.clone(), title_tx, output_names);
+            tokio::spawn(socket.serve(controller, shutdown_token.clone()))
+        });
+
+        let title_updater = relay.as_ref().and_then(|r| r.stream_id).map(|id| {
+            tokio::spawn(update_stream_title(
+                id,
+                title_rx.clone(),
+                self.server_url.clone(),
+                profile.map(String::from),
+            ))
+        });
         let mut outputs: Vec<Box<dyn session::Output>> = Vec::new();
 
-        if let Some(writer) = file_writer {
+        let mut recordings = Vec::new();
+
+        for (path, format, writer) in file_writers {
             let output = writer.start().await?;
             outputs.push(Box::new(output));
+            recordings.push((path, format));
         }
 
         let server = listener.map(|listener| {
@@ -122,32 +191,89 @@ impl cli::Session {
             outputs.push(Box::new(output));
         }
 
-        let command = &build_exec_command(command.as_ref().cloned());
-        let extra_env = &build_exec_extra_env(&self.env, relay_id.as_ref());
+        let command = &self.build_exec_command(command.as_ref().cloned());
+        child_options.env.extend(build_exec_extra_env(
+            &self.env,
+            &session_id,
+            relay_id.as_ref(),
+            socket_path.as_deref(),
+        ));
+
+        let mut driver = None;
 
-        let exit_status = {
-            let mut raw_tty = tty.open_raw().await?;
+        let outcome = {
+            let mut raw_tty = match script {
+                Some(script) => {
+                    let (script_tty, script_driver) =
+                        script.start(metadata.term.size, commands_tx.clone());
+                    driver = Some(tokio::spawn(script_driver.run()));
+
+                    Box::new(EmulatedTty::new(script_tty, tty.theme.clone()))
+                }
+
+                None => tty.open_raw().await?,
+            };
 
             session::run(
                 command,
-                extra_env,
+                &child_options,
                 raw_tty.as_mut(),
                 self.capture_input || config.session.capture_input,
+                config.session.hidden_input_marker,
                 outputs,
+                redactor,
+                command_markers,
+                commands_rx,
+                self.get_limits(),
                 keys,
                 notifier,
             )
             .await?
         };
 
+        if let Some(limit) = outcome.limit {
+            status::warning!("Session stopped, {limit}");
+        }
+
+        let script_result = match driver {
+            Some(task) if task.is_finished() => task.await?,
+
+            Some(task) => {
+                task.abort();
+                status::warning!("Command exited before the script finished");
+
+                Ok(())
+            }
+
+            None => Ok(()),
+        };
+
         status::info!("asciinema session ended");
 
-        if let Some(path) = self.output_file.as_ref() {
-            status::info!("Recorded to {}", path);
+        let title = title_rx.borrow().clone();
+        let title = title.filter(|t| Some(t) != metadata.title.as_ref() && !self.is_segmented());
+
+        for (path, format) in &recordings {
+            if let Some(title) = title.as_ref().filter(|_| is_asciicast(*format)) {
+                if let Err(e) = asciicast::update_title(path, title) {
+                    status::warning!("Failed to update the recording title: {e}");
+                }
+            }
+
+            status::info!("Recorded to {}", self.display_path(path));
         }
 
         shutdown_token.cancel();
 
+        if let Some(task) = controller {
+            let _ = task.await;
+        }
+
+        if let Some(task) = title_updater {
+            debug!("waiting for stream title update...");
+            let _ = time::timeout(Duration::from_secs(5), task).await;
+        }
+
         if let Some(task) = server {
             debug!("waiting for server shutdown...");
             let _ = time::timeout(Duration::from_secs(5), task).await;
@@ -158,13 +284,146 @@ impl cli::Session {
             let _ = time::timeout(Duration::from_secs(5), task).await;
         }
 
-        Ok(exit_status)
+        if self.upload || self.upload_and_delete || config.session.auto_upload {
+            hook_env.upload_urls = self.upload_recordings(&recordings, &mut config).await;
+        }
+
+        // after the upload, so the hook can share the uploaded recording
+        hook_env.exit_status = Some(outcome.status);
+        run_hook("post_hook", config.session.post_hook.as_ref(), &hook_env).await;
+
+        script_result?;
+
+        Ok(outcome)
+    }
+
+    /// Uploads the recordings, returning the URLs of the uploaded ones.
+    async fn upload_recordings(
+        &self,
+        recordings: &[(String, Format)],
+        config: &mut Config,
+    ) -> Vec<String> {
+        let mut urls = Vec::new();
+
+        for (path, format) in recordings {
+            if !is_asciicast(*format)
+                || self.is_segmented()
+                || Compression::from_path(Path::new(path)).is_some()
+            {
+                status::warning!(
+                    "Skipping upload of {}, not a single uncompressed asciicast file",
+                    self.display_path(path)
+                );
+                continue;
+            }
+
+            match api::create_recording(path, config).await {
+                Ok(response) => {
+                    status::info!("Uploaded to {}", response.url);
+
+                    if let Some(message) = response.message {
+                        println!("{message}");
+                    }
+
+                    if self.upload_and_delete {
+                        match fs::remove_file(path) {
+                            Ok(()) => status::info!("Deleted {path}"),
+                            Err(e) => status::warning!("Failed to delete {path}: {e}"),
+                        }
+                    }
+
+                    urls.push(response.url);
+                }
+
+                Err(e) => {
+                    status::warning!("Upload failed: {e:#}");
+                    status::warning!("Retry later with: asciinema upload {path}");
+                }
+            }
+        }
+
+        urls
     }
 
     fn get_command(&self, config: &config::Session) -> Option<String> {
+        if !self.args.is_empty() {
+            return Some(util::shell_join(&self.args));
+        }
+
         self.command.as_ref().cloned().or(config.command.clone())
     }
 
+    fn build_exec_command(&self, command: Option<String>) -> Vec<String> {
+        if !self.args.is_empty() {
+            return self.args.clone();
+        }
+
+        let command = command
+            .or(env::var("SHELL").ok())
+            .unwrap_or("/bin/sh".to_owned());
+
+        vec!["/bin/sh".to_owned(), "-c".to_owned(), command]
+    }
+
+    fn get_child_options(&self, config: &config::Session) -> Result<ChildOptions> {
+        let cwd = self.cwd.clone().or(config.cwd.as_ref().map(PathBuf::from));
+
+        if let Some(cwd) = &cwd {
+            if !cwd.is_dir() {
+                bail!("working directory {} doesn't exist", cwd.to_string_lossy());
+            }
+        }
+
+        let mut env: HashMap<String, String> = config.env()?.into_iter().collect();
+
+        for var in &self.set_env {
+            if let Some((name, value)) = var.split_once('=') {
+                env.insert(name.to_owned(), value.to_owned());
+            }
+        }
+
+        let mut unset_env = config.unset_env.clone().unwrap_or_default();
+        unset_env.extend(self.unset_env.iter().cloned());
+
+        Ok(ChildOptions {
+            cwd,
+            env,
+            unset_env,
+            clean_env: self.clean_env || config.clean_env,
+            umask: self.umask.or(config.umask()?),
+        })
+    }
+
+    fn get_limits(&self) -> Limits {
+        Limits {
+            max_duration: self.max_duration,
+            max_size: self.max_size,
+            max_idle: self.stop_after_idle,
+        }
+    }
+
+    fn get_output_names(
+        &self,
+        listener: Option<&TcpListener>,
+        relay: Option<&Relay>,
+    ) -> Vec<String> {
+        let mut names = Vec::new();
+
+        for output in &self.output_file {
+            names.push(self.display_path(&output.path));
+        }
+
+        if let Some(listener) = listener {
+            names.push(format!("http://{}", listener.local_addr().unwrap()));
+        }
+
+        if let Some(Relay { url: Some(url), .. }) = relay {
+            names.push(url.to_string());
+        }
+
+        names
+    }
+
     fn get_session_metadata(&self, config: &config::Session, term: TermInfo) -> Result<Metadata> {
         Ok(Metadata {
             time: SystemTime::now(),
@@ -176,29 +435,93 @@ impl cli::Session {
         })
     }
 
+    async fn get_file_writers<N: Notifier + Clone + 'static>(
+        &self,
+        metadata: &Metadata,
+        config: &config::Session,
+        notifier: N,
+    ) -> Result<Vec<(String, Format, FileWriter)>> {
+        let sync_policy = config.sync_policy()?;
+
+        let mut paths = HashSet::new();
+
+        for output in &self.output_file {
+            if !paths.insert(&output.path) {
+                bail!("output file {} specified more than once", output.path);
+            }
+        }
+
+        let mut writers = Vec::new();
+
+        for output in &self.output_file {
+            let (format, writer) = self
+                .get_file_writer(output, metadata, sync_policy, notifier.clone())
+                .await?;
+
+            let writer = writer.with_exit_details(config.exit_details);
+
+            writers.push((output.path.clone(), format, writer));
+        }
+
+        Ok(writers)
+    }
+
     async fn get_file_writer<N: Notifier + 'static>(
         &self,
+        output: &cli::OutputFile,
         metadata: &Metadata,
+        sync_policy: SyncPolicy,
         notifier: N,
-    ) -> Result<Option<FileWriter>> {
-        let Some(path) = self.output_file.as_ref() else {
-            return Ok(None);
+    ) -> Result<(Format, FileWriter)> {
+        let pattern = self
+            .is_segmented()
+            .then(|| segment_path_pattern(&output.path));
+
+        let path = match &pattern {
+            Some(pattern) => util::segment_path(pattern, 1, metadata.time),
+            None => output.path.clone(),
         };
 
-        let path = Path::new(path);
+        let path = Path::new(&path);
         let (overwrite, append) = self.get_file_mode(path)?;
+        let format = self.get_file_format(output.format, path, append)?;
         let file = self.open_output_file(path, overwrite, append).await?;
-        let format = self.get_file_format(path, append)?;
-        let writer = Box::new(file);
         let notifier = Box::new(notifier);
         let encoder = self.get_encoder(format, path, append)?;
+        let mut writer =
+            FileWriter::new(file, encoder, notifier, metadata.clone()).synced(sync_policy);
+
+        if let Some(compression) = Compression::from_path(path) {
+            writer = writer.compressed(compression);
+        }
+
+        match pattern {
+            Some(path_pattern) => {
+                let writer = writer.segmented(Segmentation {
+                    path_pattern,
+                    max_duration: self.segment_duration,
+                    max_size: self.segment_size,
+                    overwrite,
+                    encoder: Box::new(move || new_encoder(format, false, Duration::from_micros(0))),
+                });
+
+                Ok((format, writer))
+            }
+
+            None => Ok((format, writer)),
+        }
+    }
 
-        Ok(Some(FileWriter::new(
-            writer,
-            encoder,
-            notifier,
-            metadata.clone(),
-        )))
+    fn is_segmented(&self) -> bool {
+        self.segment_duration.is_some() || self.segment_size.is_some()
+    }
+
+    fn display_path(&self, path: &str) -> String {
+        if self.is_segmented() {
+            segment_path_pattern(path)
+        } else {
+            path.to_owned()
+        }
     }
 
     fn get_file_mode(&self, path: &Path) -> Result<(bool, bool)> {
@@ -223,10 +546,16 @@ impl cli::Session {
         Ok((overwrite, append))
     }
 
-    fn get_file_format(&self, path: &Path, append: bool) -> Result<Format> {
-        self.output_format.map(Ok).unwrap_or_else(|| {
-            if path.extension().is_some_and(|ext| ext == "txt") {
+    fn get_file_format(&self, format: Option<Format>, path: &Path, append: bool) -> Result<Format> {
+        let ext = compression::strip_extension(path)
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned());
+
+        format.or(self.output_format).map(Ok).unwrap_or_else(|| {
+            if ext.as_deref() == Some("txt") {
                 Ok(Format::Txt)
+            } else if ext.as_deref() == Some("raw") {
+                Ok(Format::Raw)
             } else if append {
                 match asciicast::open_from_path(path) {
                     Ok(cast) => match cast.version {
@@ -249,22 +578,13 @@ impl cli::Session {
         path: &Path,
         append: bool,
     ) -> Result<Box<dyn Encoder + Send>> {
-        match format {
-            Format::AsciicastV3 => Ok(Box::new(AsciicastV3Encoder::new(append))),
-
-            Format::AsciicastV2 => {
-                let time_offset = if append {
-                    asciicast::get_duration(path)?
-                } else {
-                    Duration::from_micros(0)
-                };
-
-                Ok(Box::new(AsciicastV2Encoder::new(append, time_offset)))
-            }
+        let time_offset = if append && format == Format::AsciicastV2 {
+            asciicast::get_duration(path)?
+        } else {
+            Duration::from_micros(0)
+        };
 
-            Format::Raw => Ok(Box::new(RawEncoder::new())),
-            Format::Txt => Ok(Box::new(TextEncoder::new())),
-        }
+        Ok(new_encoder(format, append, time_offset))
     }
 
     async fn open_output_file(
@@ -315,12 +635,14 @@ impl cli::Session {
                 Relay {
                     ws_producer_url: stream.ws_producer_url.parse()?,
                     url: Some(stream.url.parse()?),
+                    stream_id: Some(stream.id),
                 }
             }
 
             RelayTarget::WsProducerUrl(url) => Relay {
                 ws_producer_url: url.clone(),
                 url: None,
+                stream_id: None,
             },
         };
 
@@ -410,11 +732,13 @@ struct TtySelection {
     cols: Option<u16>,
     rows: Option<u16>,
     kind: TtyKind,
+    theme: Option<TtyTheme>,
 }
 
 async fn probe_tty(
     headless: bool,
     window_size: Option<(Option<u16>, Option<u16>)>,
+    headless_theme: Option<TtyTheme>,
 ) -> Result<(TtySelection, TermInfo)> {
     let (cols, rows) = window_size.unwrap_or((None, None));
 
@@ -437,7 +761,12 @@ async fn probe_tty(
         )
     };
 
-    let selection = TtySelection { cols, rows, kind };
+    let selection = TtySelection {
+        cols,
+        rows,
+        kind,
+        theme: headless_theme.clone(),
+    };
 
     let term_info = match kind {
         TtyKind::DevTty => {
@@ -473,7 +802,7 @@ async fn probe_tty(
             type_: None,
             version: None,
             size: tty.get_size().into(),
-            theme: None,
+            theme: headless_theme,
         },
     };
 
@@ -488,7 +817,10 @@ impl TtySelection {
                 Box::new(FixedSizeTty::new(tty, self.cols, self.rows))
             }
 
-            TtyKind::NullTty => Box::new(FixedSizeTty::new(NullTty, self.cols, self.rows)),
+            TtyKind::NullTty => {
+                let tty = FixedSizeTty::new(NullTty, self.cols, self.rows);
+                Box::new(EmulatedTty::new(tty, self.theme.clone()))
+            }
         })
     }
 }
@@ -497,6 +829,7 @@ impl TtySelection {
 struct Relay {
     ws_producer_url: Url,
     url: Option<Url>,
+    stream_id: Option<u64>,
 }
 
 impl Relay {
@@ -505,6 +838,50 @@ impl Relay {
     }
 }
 
+fn is_asciicast(format: Format) -> bool {
+    matches!(format, Format::AsciicastV3 | Format::AsciicastV2)
+}
+
+fn new_encoder(format: Format, append: bool, time_offset: Duration) -> Box<dyn Encoder + Send> {
+    match format {
+        Format::AsciicastV3 => Box::new(AsciicastV3Encoder::new(append)),
+        Format::AsciicastV2 => Box::new(AsciicastV2Encoder::new(append, time_offset)),
+        Format::Raw => Box::new(RawEncoder::new()),
+        Format::Txt => Box::new(TextEncoder::new()),
+    }
+}
+
+/// Turns the output file path into a segment path pattern, numbering the
+/// segments unless the path already contains the number placeholder.
+fn segment_path_pattern(path: &str) -> String {
+    if path.contains("{n}") {
+        return path.to_owned();
+    }
+
+    let path = Path::new(path);
+
+    // keep the compression extension last, e.g. demo-{n}.cast.gz
+    let suffix = Compression::from_path(path)
+        .and(path.extension())
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    let path = compression::strip_extension(path);
+
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(ext)) => path
+            .with_file_name(format!(
+                "{}-{{n}}.{}{suffix}",
+                stem.to_string_lossy(),
+                ext.to_string_lossy()
+            ))
+            .to_string_lossy()
+            .into_owned(),
+
+        _ => format!("{}-{{n}}{suffix}", path.to_string_lossy()),
+    }
+}
+
 fn get_key_bindings(config: &config::Session) -> Result<KeyBindings> {
     let mut keys = KeyBindings::default();
 
@@ -520,19 +897,30 @@ fn get_key_bindings(config: &config::Session) -> Result<KeyBindings> {
         keys.add_marker = key;
     }
 
+    keys.add_marker_prompt = config.add_marker_prompt;
+
     Ok(keys)
 }
 
+fn get_redactor(config: &config::Redaction) -> Result<Option<Redactor>> {
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    let rules = config.rules.clone().unwrap_or_default();
+    let redactor = Redactor::new(config.builtin_rules, &rules, config.replacement.clone())?;
+
+    Ok(Some(redactor))
+}
+
 fn capture_env(var_names: Option<String>, config: &config::Session) -> HashMap<String, String> {
-    let var_names = var_names
+    let patterns = var_names
         .or(config.capture_env.clone())
-        .unwrap_or(String::from("SHELL"));
+        .unwrap_or(String::from(util::DEFAULT_CAPTURE_ENV));
 
-    let vars = var_names.split(',').collect::<HashSet<_>>();
+    let redact = config.capture_env_redact.clone().unwrap_or_default();
 
-    env::vars()
-        .filter(|(k, _v)| vars.contains(&k.as_str()))
-        .collect::<HashMap<_, _>>()
+    util::filter_env(env::vars(), &patterns, &redact)
 }
 
 fn get_notifier(config: &Config) -> BackgroundNotifier {
@@ -545,15 +933,12 @@ fn get_notifier(config: &Config) -> BackgroundNotifier {
     notifier::background(inner)
 }
 
-fn build_exec_command(command: Option<String>) -> Vec<String> {
-    let command = command
-        .or(env::var("SHELL").ok())
-        .unwrap_or("/bin/sh".to_owned());
-
-    vec!["/bin/sh".to_owned(), "-c".to_owned(), command]
-}
-
-fn build_exec_extra_env(vars: &[String], relay_id: Option<&String>) -> HashMap<String, String> {
+fn build_exec_extra_env(
+    vars: &[String],
+    session_id: &str,
+    relay_id: Option<&String>,
+    socket_path: Option<&Path>,
+) -> HashMap<String, String> {
     let mut env = HashMap::new();
 
     for var in vars {
@@ -562,8 +947,14 @@ fn build_exec_extra_env(vars: &[String], relay_id: Option<&String>) -> HashMap<S
         }
     }
 
-    let session_id = format!("{:x}", hash::fnv1a_128(process::id().to_string()));
-    env.insert("ASCIINEMA_SESSION".to_owned(), session_id);
+    env.insert("ASCIINEMA_SESSION".to_owned(), session_id.to_owned());
+
+    if let Some(path) = socket_path {
+        env.insert(
+            control::SOCKET_ENV_VAR.to_owned(),
+            path.to_string_lossy().to_string(),
+        );
+    }
 
     if let Some(id) = relay_id {
         env.insert("ASCIINEMA_RELAY_ID".to_owned(), id.clone());
@@ -572,6 +963,63 @@ fn build_exec_extra_env(vars: &[String], relay_id: Option<&String>) -> HashMap<S
     env
 }
 
+fn get_stream_url(listener: Option<&TcpListener>, relay: Option<&Relay>) -> Option<String> {
+    match (relay, listener) {
+        (Some(Relay { url: Some(url), .. }), _) => Some(url.to_string()),
+        (_, Some(listener)) => Some(format!("http://{}", listener.local_addr().unwrap())),
+        _ => None,
+    }
+}
+
+async fn run_hook(name: &str, command: Option<&String>, env: &HookEnv) {
+    if let Some(command) = command {
+        if let Err(e) = hook::run(command, env).await {
+            status::warning!("session.{name} failed: {e}");
+        }
+    }
+}
+
+fn get_session_id() -> String {
+    format!("{:x}", hash::fnv1a_128(process::id().to_string()))
+}
+
+fn get_control_socket(session_id: &str) -> Option<Socket> {
+    match Socket::bind(session_id) {
+        Ok(socket) => Some(socket),
+
+        Err(e) => {
+            status::warning!("Control socket not available: {e:#}");
+            None
+        }
+    }
+}
+
+async fn update_stream_title(
+    stream_id: u64,
+    mut title_rx: watch::Receiver<Option<String>>,
+    server_url: Option<String>,
+    profile: Option<String>,
+) {
+    while title_rx.changed().await.is_ok() {
+        let changeset = StreamChangeset {
+            title: Some(title_rx.borrow_and_update().clone()),
+            ..Default::default()
+        };
+
+        let result = match Config::new(server_url.clone(), profile.as_deref()) {
+            Ok(mut config) => api::update_stream(stream_id, changeset, &mut config)
+                .await
+                .map(|_| ()),
+
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            error!("stream title update failed: {e}");
+        }
+    }
+}
+
 fn get_parent_session_relay_id() -> Option<String> {
     env::var("ASCIINEMA_RELAY_ID").ok()
 }
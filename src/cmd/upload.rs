@@ -7,12 +7,12 @@ use crate::cli;
 use crate::config::Config;
 
 impl cli::Upload {
-    pub fn run(self) -> Result<()> {
-        Runtime::new()?.block_on(self.do_run())
+    pub fn run(self, profile: Option<&str>) -> Result<()> {
+        Runtime::new()?.block_on(self.do_run(profile))
     }
 
-    async fn do_run(self) -> Result<()> {
-        let mut config = Config::new(self.server_url.clone())?;
+    async fn do_run(self, profile: Option<&str>) -> Result<()> {
+        let mut config = Config::new(self.server_url.clone(), profile)?;
         let _ = asciicast::open_from_path(&self.file)?;
         let response = api::create_recording(&self.file, &mut config).await?;
         println!("{}", response.message.unwrap_or(response.url));
@@ -0,0 +1,216 @@
+use crate::ansi::{Scanner, Token};
+
+/// Turns OSC 133 shell integration sequences found in terminal output into
+/// marker labels.
+///
+/// Shells with OSC 133 support wrap the prompt and the command line with
+/// `ESC ] 133 ; A` (prompt start), `ESC ] 133 ; B` (command input start),
+/// `ESC ] 133 ; C` (command executed) and `ESC ] 133 ; D ; <status>` (command
+/// finished). A marker labelled with the command line is produced when a command
+/// starts, and another one, including the exit status, when it finishes.
+///
+/// The command line is taken from the `cmdline` / `cmdline_url` parameter of the
+/// `C` sequence when present, otherwise from the text echoed between `B` and `C`.
+pub struct CommandMarkers {
+    scanner: Scanner,
+    input: Option<String>,
+    command: Option<String>,
+    commands: usize,
+}
+
+const MAX_SEQUENCE_LEN: usize = 4096;
+const MAX_COMMAND_LEN: usize = 1024;
+
+impl CommandMarkers {
+    pub fn new() -> Self {
+        Self {
+            scanner: Scanner::new(MAX_SEQUENCE_LEN),
+            input: None,
+            command: None,
+            commands: 0,
+        }
+    }
+
+    pub fn feed(&mut self, text: &str) -> Vec<String> {
+        let mut labels = Vec::new();
+
+        for c in text.chars() {
+            match self.scanner.feed(c) {
+                Some(Token::Char('\x08' | '\x7f')) => {
+                    if let Some(input) = self.input.as_mut() {
+                        input.pop();
+                    }
+                }
+
+                Some(Token::Char(c)) if !c.is_control() => {
+                    if let Some(input) = self.input.as_mut() {
+                        if input.len() < MAX_COMMAND_LEN {
+                            input.push(c);
+                        }
+                    }
+                }
+
+                Some(Token::Osc(data, _)) => {
+                    labels.extend(self.handle_osc(&data));
+                }
+
+                _ => {}
+            }
+        }
+
+        labels
+    }
+
+    /// Number of commands started so far.
+    pub fn commands(&self) -> usize {
+        self.commands
+    }
+
+    fn handle_osc(&mut self, data: &str) -> Option<String> {
+        let mut params = data.strip_prefix("133;")?.split(';');
+
+        match params.next()? {
+            "A" => {
+                self.input = None;
+
+                None
+            }
+
+            "B" => {
+                self.input = Some(String::new());
+
+                None
+            }
+
+            "C" => {
+                let input = self.input.take();
+
+                let command = params
+                    .find_map(parse_cmdline_param)
+                    .or(input)
+                    .map(|c| c.trim().to_owned())
+                    .filter(|c| !c.is_empty());
+
+                self.command.clone_from(&command);
+                self.commands += command.is_some() as usize;
+
+                command
+            }
+
+            "D" => {
+                let command = self.command.take()?;
+
+                match params.next().and_then(|s| s.parse::<i32>().ok()) {
+                    Some(status) => Some(format!("{command} (exit status {status})")),
+                    None => Some(format!("{command} (finished)")),
+                }
+            }
+
+            _ => None,
+        }
+    }
+}
+
+fn parse_cmdline_param(param: &str) -> Option<String> {
+    if let Some(cmdline) = param.strip_prefix("cmdline=") {
+        Some(cmdline.to_owned())
+    } else {
+        param.strip_prefix("cmdline_url=").map(percent_decode)
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CommandMarkers;
+
+    fn feed(chunks: &[&str]) -> Vec<String> {
+        let mut markers = CommandMarkers::new();
+
+        chunks
+            .iter()
+            .flat_map(|chunk| markers.feed(chunk))
+            .collect()
+    }
+
+    #[test]
+    fn echoed_command_line() {
+        let labels = feed(&[
+            "\x1b]133;A\x07$ \x1b]133;B\x07",
+            "l",
+            "s -la\r\n\x1b]133;C\x07",
+            "total 0\r\n\x1b]133;D;0\x07",
+            "\x1b]133;A\x07$ \x1b]133;B\x07",
+            "mak\x08\x1b[K",
+            "ke test\r\n\x1b]133;C\x1b\\",
+            "FAIL\r\n\x1b]133;D;2\x1b\\\x1b]133;A\x1b\\$ ",
+        ]);
+
+        assert_eq!(
+            labels,
+            vec![
+                "ls -la",
+                "ls -la (exit status 0)",
+                "make test",
+                "make test (exit status 2)"
+            ]
+        );
+    }
+
+    #[test]
+    fn split_sequences() {
+        let labels = feed(&[
+            "$ \x1b]13",
+            "3;B\x07echo hi\r\n\x1b",
+            "]133;C\x07hi\r\n\x1b]133;D\x07",
+        ]);
+
+        assert_eq!(labels, vec!["echo hi", "echo hi (finished)"]);
+    }
+
+    #[test]
+    fn cmdline_param() {
+        let labels = feed(&[
+            "\x1b]133;B\x07garbage\x1b]133;C;cmdline=cargo build\x07",
+            "\x1b]133;D;1\x07",
+            "\x1b]133;B\x07\x1b]133;C;cmdline_url=echo%20a%3Bb\x07",
+        ]);
+
+        assert_eq!(
+            labels,
+            vec!["cargo build", "cargo build (exit status 1)", "echo a;b"]
+        );
+    }
+
+    #[test]
+    fn no_command() {
+        let labels = feed(&[
+            "\x1b]133;D;0\x07\x1b]133;A\x07$ \x1b]133;B\x07\r\n\x1b]133;C\x07\x1b]133;D;0\x07",
+            "\x1b]0;title\x07\x1b]1337;foo\x07",
+        ]);
+
+        assert!(labels.is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+/// Compressing writer, finishing the compressed stream when told to.
+pub enum Writer<W: Write> {
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+/// Compresses data in memory, for writers which can't block, e.g. async ones.
+pub struct Compressor(Writer<Vec<u8>>);
+
+impl Compression {
+    /// Picks compression based on the file extension, .gz or .zst.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "gz" => Some(Self::Gzip),
+            "zst" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(GZIP_MAGIC) {
+            Some(Self::Gzip)
+        } else if data.starts_with(ZSTD_MAGIC) {
+            Some(Self::Zstd)
+        } else {
+            None
+        }
+    }
+
+    pub fn writer<W: Write>(self, writer: W) -> io::Result<Writer<W>> {
+        match self {
+            Self::Gzip => Ok(Writer::Gzip(GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            ))),
+
+            Self::Zstd => Ok(Writer::Zstd(zstd::Encoder::new(writer, ZSTD_LEVEL)?)),
+        }
+    }
+
+    pub fn compressor(self) -> io::Result<Compressor> {
+        self.writer(Vec::new()).map(Compressor)
+    }
+}
+
+impl<W: Write> Writer<W> {
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Zstd(encoder) => encoder.finish(),
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut W {
+        match self {
+            Self::Gzip(encoder) => encoder.get_mut(),
+            Self::Zstd(encoder) => encoder.get_mut(),
+        }
+    }
+
+    fn try_finish(&mut self) -> io::Result<()> {
+        match self {
+            Self::Gzip(encoder) => encoder.try_finish(),
+            Self::Zstd(encoder) => encoder.do_finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Gzip(encoder) => encoder.write(buf),
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Gzip(encoder) => encoder.flush(),
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+impl Compressor {
+    /// Returns compressed data which is ready to be written out.
+    pub fn compress(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        self.0.write_all(data)?;
+
+        Ok(mem::take(self.0.get_mut()))
+    }
+
+    /// Returns all data compressed so far, decodable without the data that follows.
+    pub fn flush(&mut self) -> io::Result<Vec<u8>> {
+        self.0.flush()?;
+
+        Ok(mem::take(self.0.get_mut()))
+    }
+
+    /// Returns the remaining data, terminating the compressed stream.
+    pub fn finish(&mut self) -> io::Result<Vec<u8>> {
+        self.0.try_finish()?;
+
+        Ok(mem::take(self.0.get_mut()))
+    }
+}
+
+/// Wraps the reader with a decompressor if the data it provides is compressed.
+pub fn decoder<'a, R: BufRead + Send + 'a>(
+    mut reader: R,
+) -> io::Result<Box<dyn BufRead + Send + 'a>> {
+    match Compression::detect(reader.fill_buf()?) {
+        Some(Compression::Gzip) => Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader)))),
+        Some(Compression::Zstd) => Ok(Box::new(BufReader::new(zstd::Decoder::with_buffer(
+            reader,
+        )?))),
+        None => Ok(Box::new(reader)),
+    }
+}
+
+/// Strips the compression extension, e.g. demo.cast.gz becomes demo.cast.
+pub fn strip_extension(path: &Path) -> PathBuf {
+    if Compression::from_path(path).is_some() {
+        path.with_extension("")
+    } else {
+        path.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::path::Path;
+
+    use super::{decoder, strip_extension, Compression};
+
+    fn decompress(data: &[u8]) -> String {
+        let mut text = String::new();
+        decoder(data).unwrap().read_to_string(&mut text).unwrap();
+
+        text
+    }
+
+    #[test]
+    fn writer_roundtrip() {
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let mut writer = compression.writer(Vec::new()).unwrap();
+            writer.write_all(b"hello\n").unwrap();
+            let data = writer.finish().unwrap();
+
+            assert_eq!(Compression::detect(&data), Some(compression));
+            assert_eq!(decompress(&data), "hello\n");
+        }
+
+        assert_eq!(decompress(b"plain\n"), "plain\n");
+    }
+
+    #[test]
+    fn compressor_flush() {
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let mut compressor = compression.compressor().unwrap();
+            let mut data = compressor.compress(b"first\n").unwrap();
+            data.extend(compressor.flush().unwrap());
+
+            // everything written before the flush is readable from a truncated stream
+            let mut text = String::new();
+            let _ = decoder(&data[..]).unwrap().read_to_string(&mut text);
+            assert_eq!(text, "first\n");
+
+            data.extend(compressor.compress(b"second\n").unwrap());
+            data.extend(compressor.finish().unwrap());
+
+            // concatenated streams, e.g. after appending, are read in full
+            let mut compressor = compression.compressor().unwrap();
+            data.extend(compressor.compress(b"third\n").unwrap());
+            data.extend(compressor.finish().unwrap());
+
+            assert_eq!(decompress(&data), "first\nsecond\nthird\n");
+        }
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(Compression::from_path("a.cast.gz"), Some(Compression::Gzip));
+        assert_eq!(
+            Compression::from_path("a.cast.zst"),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::from_path("a.cast"), None);
+        assert_eq!(strip_extension(Path::new("a.txt.gz")), Path::new("a.txt"));
+        assert_eq!(strip_extension(Path::new("a.cast")), Path::new("a.cast"));
+    }
+}
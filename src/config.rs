@@ -9,10 +9,13 @@ use reqwest::Url;
 use serde::Deserialize;
 use uuid::Uuid;
 
+use crate::file_writer::SyncPolicy;
 use crate::status;
+use crate::tty::{self, TtyTheme};
 
 const DEFAULT_SERVER_URL: &str = "https://asciinema.org";
 const INSTALL_ID_FILENAME: &str = "install-id";
+const PROFILE_SECTIONS: [&str; 3] = ["session", "playback", "server"];
 
 pub type Key = Option<Vec<u8>>;
 
@@ -23,6 +26,7 @@ pub struct Config {
     pub session: Session,
     pub playback: Playback,
     pub notifications: Notifications,
+    pub redaction: Redaction,
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,11 +40,27 @@ pub struct Server {
 pub struct Session {
     pub command: Option<String>,
     pub capture_input: bool,
+    pub hidden_input_marker: bool,
     pub capture_env: Option<String>,
+    pub capture_env_redact: Option<String>,
+    pub cwd: Option<String>,
+    pub env: Option<Vec<String>>,
+    pub unset_env: Option<Vec<String>>,
+    pub clean_env: bool,
+    pub umask: Option<String>,
     pub idle_time_limit: Option<f64>,
     pub prefix_key: Option<String>,
     pub pause_key: Option<String>,
     pub add_marker_key: Option<String>,
+    pub add_marker_prompt: bool,
+    pub command_markers: bool,
+    pub exit_details: bool,
+    pub sync: Option<String>,
+    pub headless_theme: Option<String>,
+    pub window_size: Option<String>,
+    pub pre_hook: Option<String>,
+    pub post_hook: Option<String>,
+    pub auto_upload: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -51,6 +71,25 @@ pub struct Playback {
     pub pause_key: Option<String>,
     pub step_key: Option<String>,
     pub next_marker_key: Option<String>,
+    pub prev_marker_key: Option<String>,
+    pub seek_backward_key: Option<String>,
+    pub seek_forward_key: Option<String>,
+    pub seek_backward_long_key: Option<String>,
+    pub seek_forward_long_key: Option<String>,
+    pub status_key: Option<String>,
+    pub speed_up_key: Option<String>,
+    pub slow_down_key: Option<String>,
+    pub reset_speed_key: Option<String>,
+    pub idle_time_limit_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(unused)]
+pub struct Redaction {
+    pub enabled: bool,
+    pub builtin_rules: bool,
+    pub rules: Option<Vec<String>>,
+    pub replacement: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -61,16 +100,30 @@ pub struct Notifications {
 }
 
 impl Config {
-    pub fn new(server_url: Option<String>) -> Result<Self> {
+    /// Loads the config files, with the settings of the named profile, if
+    /// any, applied over them.
+    pub fn new(server_url: Option<String>, profile: Option<&str>) -> Result<Self> {
         let mut config = config::Config::builder()
             .set_default("server.url", None::<Option<String>>)?
             .set_default("playback.speed", None::<Option<f64>>)?
             .set_default("session.capture_input", false)?
+            .set_default("session.hidden_input_marker", false)?
+            .set_default("session.add_marker_prompt", false)?
+            .set_default("session.command_markers", false)?
+            .set_default("session.exit_details", false)?
+            .set_default("session.auto_upload", false)?
+            .set_default("session.clean_env", false)?
             .set_default("notifications.enabled", true)?
+            .set_default("redaction.enabled", false)?
+            .set_default("redaction.builtin_rules", true)?
             .add_source(File::with_name("/etc/asciinema/config.toml").required(false))
             .add_source(File::with_name(&user_defaults_path()?.to_string_lossy()).required(false))
             .add_source(File::with_name(&user_config_path()?.to_string_lossy()).required(false));
 
+        if let Some(name) = profile {
+            config = apply_profile(config, name)?;
+        }
+
         // legacy env var
         if let Ok(url) = env::var("ASCIINEMA_API_URL") {
             config = config.set_override("server.url", Some(url))?;
@@ -130,6 +183,81 @@ impl Session {
     pub fn add_marker_key(&self) -> Result<Option<Key>> {
         self.add_marker_key.as_ref().map(parse_key).transpose()
     }
+
+    pub fn umask(&self) -> Result<Option<u32>> {
+        self.umask
+            .as_deref()
+            .map(|mask| {
+                crate::cli::parse_umask(mask).map_err(|e| anyhow!("invalid session.umask: {e}"))
+            })
+            .transpose()
+    }
+
+    pub fn sync_policy(&self) -> Result<SyncPolicy> {
+        match self.sync.as_deref() {
+            None | Some("none") => Ok(SyncPolicy::None),
+            Some("periodic") => Ok(SyncPolicy::Periodic),
+            Some("always") => Ok(SyncPolicy::Always),
+            Some(policy) => {
+                bail!("invalid session.sync '{policy}', expected none, periodic or always")
+            }
+        }
+    }
+
+    pub fn env(&self) -> Result<Vec<(String, String)>> {
+        self.env
+            .iter()
+            .flatten()
+            .map(|var| match var.split_once('=') {
+                Some((name, value)) if !name.is_empty() => Ok((name.to_owned(), value.to_owned())),
+                _ => bail!("invalid session.env entry '{var}', expected KEY=VALUE"),
+            })
+            .collect()
+    }
+
+    pub fn window_size(&self) -> Result<Option<(Option<u16>, Option<u16>)>> {
+        self.window_size
+            .as_deref()
+            .map(|size| {
+                crate::cli::parse_window_size(size)
+                    .map_err(|_| anyhow!("invalid session.window_size, expected COLSxROWS"))
+            })
+            .transpose()
+    }
+
+    pub fn headless_theme(&self) -> Result<Option<TtyTheme>> {
+        self.headless_theme
+            .as_deref()
+            .map(|theme| {
+                parse_theme(theme).ok_or(anyhow!(
+                    "invalid session.headless_theme, expected #FG:#BG followed by 8 or 16 palette colors"
+                ))
+            })
+            .transpose()
+    }
+}
+
+/// Selects the profile applied by `Config::new`, i.e. the `[profiles.NAME]`
+/// table of the config file.
+type ConfigBuilder = config::ConfigBuilder<config::builder::DefaultState>;
+
+fn apply_profile(mut builder: ConfigBuilder, name: &str) -> Result<ConfigBuilder> {
+    let profile = builder
+        .build_cloned()?
+        .get_table(&format!("profiles.{name}"))
+        .map_err(|_| anyhow!("profile {name} not found in the config file"))?;
+
+    for (section, settings) in profile {
+        if !PROFILE_SECTIONS.contains(&section.as_str()) {
+            bail!("invalid profiles.{name}.{section}, expected session, playback or server");
+        }
+
+        for (key, value) in settings.into_table()? {
+            builder = builder.set_override(format!("{section}.{key}"), value)?;
+        }
+    }
+
+    Ok(builder)
 }
 
 impl Playback {
@@ -144,6 +272,52 @@ impl Playback {
     pub fn next_marker_key(&self) -> Result<Option<Key>> {
         self.next_marker_key.as_ref().map(parse_key).transpose()
     }
+
+    pub fn prev_marker_key(&self) -> Result<Option<Key>> {
+        self.prev_marker_key.as_ref().map(parse_key).transpose()
+    }
+
+    pub fn seek_backward_key(&self) -> Result<Option<Key>> {
+        self.seek_backward_key.as_ref().map(parse_key).transpose()
+    }
+
+    pub fn seek_forward_key(&self) -> Result<Option<Key>> {
+        self.seek_forward_key.as_ref().map(parse_key).transpose()
+    }
+
+    pub fn seek_backward_long_key(&self) -> Result<Option<Key>> {
+        self.seek_backward_long_key
+            .as_ref()
+            .map(parse_key)
+            .transpose()
+    }
+
+    pub fn seek_forward_long_key(&self) -> Result<Option<Key>> {
+        self.seek_forward_long_key
+            .as_ref()
+            .map(parse_key)
+            .transpose()
+    }
+
+    pub fn status_key(&self) -> Result<Option<Key>> {
+        self.status_key.as_ref().map(parse_key).transpose()
+    }
+
+    pub fn speed_up_key(&self) -> Result<Option<Key>> {
+        self.speed_up_key.as_ref().map(parse_key).transpose()
+    }
+
+    pub fn slow_down_key(&self) -> Result<Option<Key>> {
+        self.slow_down_key.as_ref().map(parse_key).transpose()
+    }
+
+    pub fn reset_speed_key(&self) -> Result<Option<Key>> {
+        self.reset_speed_key.as_ref().map(parse_key).transpose()
+    }
+
+    pub fn idle_time_limit_key(&self) -> Result<Option<Key>> {
+        self.idle_time_limit_key.as_ref().map(parse_key).transpose()
+    }
 }
 
 fn ask_for_server_url() -> Result<String> {
@@ -250,7 +424,35 @@ fn state_home() -> Result<PathBuf> {
         .map_err(|_| anyhow!("need $HOME or $XDG_STATE_HOME or $ASCIINEMA_STATE_HOME"))
 }
 
-fn parse_key<S: AsRef<str>>(key: S) -> Result<Key> {
+fn parse_theme(theme: &str) -> Option<TtyTheme> {
+    let mut colors = theme
+        .split(':')
+        .map(|color| {
+            let hex = color.trim().strip_prefix('#')?;
+
+            u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)
+                .map(tty::rgb)
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    if colors.len() == 10 {
+        colors.extend_from_within(2..);
+    } else if colors.len() != 18 {
+        return None;
+    }
+
+    let palette = colors.split_off(2);
+
+    Some(TtyTheme {
+        fg: colors[0],
+        bg: colors[1],
+        palette,
+    })
+}
+
+pub fn parse_key<S: AsRef<str>>(key: S) -> Result<Key> {
     let key = key.as_ref();
     let chars: Vec<char> = key.chars().collect();
 
@@ -264,23 +466,19 @@ fn parse_key<S: AsRef<str>>(key: S) -> Result<Key> {
             return Ok(Some(str.as_bytes().into()));
         }
 
-        2 => {
-            if chars[0] == '^' && chars[1].is_ascii_alphabetic() {
-                let key = vec![chars[1].to_ascii_uppercase() as u8 - 0x40];
+        2 if chars[0] == '^' && chars[1].is_ascii_alphabetic() => {
+            let key = vec![chars[1].to_ascii_uppercase() as u8 - 0x40];
 
-                return Ok(Some(key));
-            }
+            return Ok(Some(key));
         }
 
-        3 => {
-            if chars[0].eq_ignore_ascii_case(&'C')
-                && ['+', '-'].contains(&chars[1])
-                && chars[2].is_ascii_alphabetic()
-            {
-                let key = vec![chars[2].to_ascii_uppercase() as u8 - 0x40];
+        3 if chars[0].eq_ignore_ascii_case(&'C')
+            && ['+', '-'].contains(&chars[1])
+            && chars[2].is_ascii_alphabetic() =>
+        {
+            let key = vec![chars[2].to_ascii_uppercase() as u8 - 0x40];
 
-                return Ok(Some(key));
-            }
+            return Ok(Some(key));
         }
 
         _ => (),
@@ -312,3 +510,33 @@ pub fn check_legacy_config_file() {
         status::warning!("Read the documentation (CLI -> Configuration) for details.\n");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use config::{File, FileFormat};
+
+    fn builder(toml: &str) -> super::ConfigBuilder {
+        config::Config::builder().add_source(File::from_str(toml, FileFormat::Toml))
+    }
+
+    #[test]
+    fn apply_profile() {
+        let toml = "[session]\nidle_time_limit = 2.0\ncapture_input = true\n\n\
+                    [profiles.demo.session]\nidle_time_limit = 1.5\n\n\
+                    [profiles.bad.recording]\ncapture_input = false\n";
+
+        let config = super::apply_profile(builder(toml), "demo")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(config.get_float("session.idle_time_limit").unwrap(), 1.5);
+        assert!(config.get_bool("session.capture_input").unwrap());
+
+        let config = builder(toml).build().unwrap();
+
+        assert_eq!(config.get_float("session.idle_time_limit").unwrap(), 2.0);
+        assert!(super::apply_profile(builder(toml), "nope").is_err());
+        assert!(super::apply_profile(builder(toml), "bad").is_err());
+    }
+}
@@ -0,0 +1,272 @@
+use std::env;
+use std::fs;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+use crate::session;
+
+pub const SOCKET_ENV_VAR: &str = "ASCIINEMA_CTL_SOCKET";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Request {
+    Marker { label: String },
+    Pause,
+    Resume,
+    Title { title: String },
+    Status,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Status {
+    pub elapsed: f64,
+    pub paused: bool,
+    pub title: Option<String>,
+    pub outputs: Vec<String>,
+}
+
+pub struct Controller {
+    commands_tx: mpsc::Sender<session::Command>,
+    title_tx: watch::Sender<Option<String>>,
+    outputs: Vec<String>,
+}
+
+pub struct Socket {
+    path: PathBuf,
+    listener: UnixListener,
+}
+
+impl Controller {
+    pub fn new(
+        commands_tx: mpsc::Sender<session::Command>,
+        title_tx: watch::Sender<Option<String>>,
+        outputs: Vec<String>,
+    ) -> Self {
+        Self {
+            commands_tx,
+            title_tx,
+            outputs,
+        }
+    }
+
+    async fn handle(&self, request: Request) -> Response {
+        match self.do_handle(request).await {
+            Ok(status) => Response {
+                error: None,
+                status,
+            },
+
+            Err(e) => Response {
+                error: Some(e.to_string()),
+                status: None,
+            },
+        }
+    }
+
+    async fn do_handle(&self, request: Request) -> Result<Option<Status>> {
+        match request {
+            Request::Marker { label } => {
+                self.send(session::Command::Marker(label)).await?;
+            }
+
+            Request::Pause => {
+                self.send(session::Command::Pause).await?;
+            }
+
+            Request::Resume => {
+                self.send(session::Command::Resume).await?;
+            }
+
+            Request::Title { title } => {
+                self.title_tx.send_replace(Some(title));
+            }
+
+            Request::Status => {
+                let (tx, rx) = oneshot::channel();
+                self.send(session::Command::Status(tx)).await?;
+                let status = rx.await?;
+
+                return Ok(Some(Status {
+                    elapsed: status.elapsed_time.as_secs_f64(),
+                    paused: status.paused,
+                    title: self.title_tx.borrow().clone(),
+                    outputs: self.outputs.clone(),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn send(&self, command: session::Command) -> Result<()> {
+        self.commands_tx
+            .send(command)
+            .await
+            .map_err(|_| anyhow!("session has ended"))
+    }
+}
+
+impl Socket {
+    pub fn bind(session_id: &str) -> Result<Self> {
+        let dir = socket_dir();
+
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)
+            .with_context(|| format!("cannot create {}", dir.to_string_lossy()))?;
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+
+        let path = dir.join(format!("{session_id}.sock"));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("cannot bind {}", path.to_string_lossy()))?;
+
+        Ok(Self { path, listener })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub async fn serve(self, controller: Controller, shutdown_token: CancellationToken) {
+        let controller = Arc::new(controller);
+
+        loop {
+            tokio::select! {
+                result = self.listener.accept() => {
+                    match result {
+                        Ok((stream, _addr)) => {
+                            tokio::spawn(handle_connection(stream, controller.clone()));
+                        }
+
+                        Err(e) => {
+                            error!("control socket accept failed: {e}");
+                            break;
+                        }
+                    }
+                }
+
+                _ = shutdown_token.cancelled() => break,
+            }
+        }
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+async fn handle_connection(stream: UnixStream, controller: Arc<Controller>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        debug!("control request: {line}");
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => controller.handle(request).await,
+
+            Err(e) => Response {
+                error: Some(format!("invalid request: {e}")),
+                status: None,
+            },
+        };
+
+        let mut data = serde_json::to_vec(&response).unwrap();
+        data.push(b'\n');
+
+        if writer.write_all(&data).await.is_err() {
+            break;
+        }
+    }
+}
+
+pub async fn request(path: &Path, request: &Request) -> Result<Response> {
+    let stream = UnixStream::connect(path)
+        .await
+        .with_context(|| format!("cannot connect to {}", path.to_string_lossy()))?;
+
+    let (reader, mut writer) = stream.into_split();
+    let mut data = serde_json::to_vec(request)?;
+    data.push(b'\n');
+    writer.write_all(&data).await?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or(anyhow!("connection closed by the session"))?;
+
+    let response = serde_json::from_str::<Response>(&line)?;
+
+    if let Some(error) = response.error {
+        bail!(error);
+    }
+
+    Ok(response)
+}
+
+fn socket_dir() -> PathBuf {
+    if let Ok(dir) = env::var("XDG_RUNTIME_DIR") {
+        return Path::new(&dir).join("asciinema");
+    }
+
+    let uid = nix::unistd::getuid();
+
+    env::temp_dir().join(format!("asciinema-{uid}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Request, Response};
+
+    #[test]
+    fn request_serialization() {
+        let request = Request::Marker {
+            label: "tests started".to_owned(),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"command":"marker","label":"tests started"}"#
+        );
+
+        assert_eq!(
+            serde_json::to_string(&Request::Pause).unwrap(),
+            r#"{"command":"pause"}"#
+        );
+
+        assert!(matches!(
+            serde_json::from_str::<Request>(r#"{"command":"title","title":"Demo"}"#).unwrap(),
+            Request::Title { title } if title == "Demo"
+        ));
+
+        assert!(serde_json::from_str::<Request>(r#"{"command":"reboot"}"#).is_err());
+    }
+
+    #[test]
+    fn response_serialization() {
+        assert_eq!(serde_json::to_string(&Response::default()).unwrap(), "{}");
+    }
+}
@@ -2,7 +2,6 @@ mod asciicast;
 mod raw;
 mod txt;
 
-use std::fs::File;
 use std::io::Write;
 
 use anyhow::Result;
@@ -19,11 +18,19 @@ pub trait Encoder {
 }
 
 pub trait EncoderExt {
-    fn encode_to_file(&mut self, cast: crate::asciicast::Asciicast, file: &mut File) -> Result<()>;
+    fn encode_to_file(
+        &mut self,
+        cast: crate::asciicast::Asciicast,
+        file: &mut dyn Write,
+    ) -> Result<()>;
 }
 
 impl<E: Encoder + ?Sized> EncoderExt for E {
-    fn encode_to_file(&mut self, cast: crate::asciicast::Asciicast, file: &mut File) -> Result<()> {
+    fn encode_to_file(
+        &mut self,
+        cast: crate::asciicast::Asciicast,
+        file: &mut dyn Write,
+    ) -> Result<()> {
         file.write_all(&self.header(&cast.header))?;
 
         for event in cast.events {
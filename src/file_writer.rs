@@ -1,29 +1,80 @@
-use std::time::UNIX_EPOCH;
+use std::borrow::Cow;
+use std::time::{Duration, UNIX_EPOCH};
 
 use async_trait::async_trait;
-use tokio::io::{self, AsyncWrite, AsyncWriteExt};
+use avt::Vt;
+use tokio::fs::File;
+use tokio::io::{self, AsyncWriteExt};
 
 use crate::asciicast;
+use crate::compression::{Compression, Compressor};
 use crate::encoder::Encoder;
 use crate::notifier::Notifier;
+use crate::pty::ExitStatus;
 use crate::session::{self, Metadata};
+use crate::tty::TtySize;
+use crate::util;
 
 pub struct FileWriter {
-    writer: Box<dyn AsyncWrite + Send + Unpin>,
+    writer: File,
     encoder: Box<dyn Encoder + Send>,
     notifier: Box<dyn Notifier>,
     metadata: Metadata,
+    compression: Option<Compression>,
+    sync_policy: SyncPolicy,
+    segmentation: Option<Segmentation>,
+    exit_details: bool,
 }
 
 pub struct LiveFileWriter {
-    writer: Box<dyn AsyncWrite + Send + Unpin>,
+    writer: File,
     encoder: Box<dyn Encoder + Send>,
     notifier: Box<dyn Notifier>,
+    compression: Option<Compression>,
+    compressor: Option<Compressor>,
+    sync_policy: SyncPolicy,
+    unflushed: bool,
+    segments: Option<Segments>,
+    exit_details: bool,
+}
+
+/// When the recorded data is forced to disk with fsync, making sure it
+/// survives a crash of the whole system.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SyncPolicy {
+    /// Leave it to the operating system.
+    #[default]
+    None,
+    /// Sync once a second, see `session::OUTPUT_TICK_INTERVAL`.
+    Periodic,
+    /// Sync after every event.
+    Always,
+}
+
+/// Rolls the recording over to a new file once the current one reaches the
+/// configured duration or size.
+pub struct Segmentation {
+    /// Path of segment files, see `util::segment_path`.
+    pub path_pattern: String,
+    pub max_duration: Option<Duration>,
+    pub max_size: Option<u64>,
+    pub overwrite: bool,
+    pub encoder: Box<dyn Fn() -> Box<dyn Encoder + Send> + Send>,
+}
+
+struct Segments {
+    config: Segmentation,
+    metadata: Metadata,
+    number: usize,
+    start_time: Duration,
+    size: u64,
+    events: usize,
+    vt: Vt,
 }
 
 impl FileWriter {
     pub fn new(
-        writer: Box<dyn AsyncWrite + Send + Unpin>,
+        writer: File,
         encoder: Box<dyn Encoder + Send>,
         notifier: Box<dyn Notifier>,
         metadata: Metadata,
@@ -33,32 +84,61 @@ impl FileWriter {
             encoder,
             notifier,
             metadata,
+            compression: None,
+            sync_policy: SyncPolicy::None,
+            segmentation: None,
+            exit_details: false,
         }
     }
 
+    pub fn synced(mut self, policy: SyncPolicy) -> Self {
+        self.sync_policy = policy;
+
+        self
+    }
+
+    pub fn compressed(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+
+        self
+    }
+
+    pub fn segmented(mut self, segmentation: Segmentation) -> Self {
+        self.segmentation = Some(segmentation);
+
+        self
+    }
+
+    /// Writes an exit details event before the exit event, see
+    /// `asciicast::ExitDetails`.
+    pub fn with_exit_details(mut self, enabled: bool) -> Self {
+        self.exit_details = enabled;
+
+        self
+    }
+
     pub async fn start(mut self) -> io::Result<LiveFileWriter> {
-        let timestamp = self
-            .metadata
-            .time
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        let header = asciicast::Header {
-            term_cols: self.metadata.term.size.0,
-            term_rows: self.metadata.term.size.1,
-            term_type: self.metadata.term.type_.clone(),
-            term_version: self.metadata.term.version.clone(),
-            term_theme: self.metadata.term.theme.clone(),
-            timestamp: Some(timestamp),
-            idle_time_limit: self.metadata.idle_time_limit,
-            command: self.metadata.command.as_ref().cloned(),
-            title: self.metadata.title.as_ref().cloned(),
-            env: Some(self.metadata.env.clone()),
+        let header = self.encoder.header(&build_header(&self.metadata));
+        let compressor = self.compression.map(|c| c.compressor()).transpose()?;
+
+        let segments = self
+            .segmentation
+            .map(|config| Segments::new(config, self.metadata));
+
+        let mut writer = LiveFileWriter {
+            writer: self.writer,
+            encoder: self.encoder,
+            notifier: self.notifier,
+            compression: self.compression,
+            compressor,
+            sync_policy: self.sync_policy,
+            unflushed: false,
+            segments,
+            exit_details: self.exit_details,
         };
 
-        if let Err(e) = self.writer.write_all(&self.encoder.header(&header)).await {
-            let _ = self
+        if let Err(e) = writer.write(&header).await {
+            let _ = writer
                 .notifier
                 .notify("Write error, session won't be recorded".to_owned())
                 .await;
@@ -66,22 +146,131 @@ impl FileWriter {
             return Err(e);
         }
 
-        Ok(LiveFileWriter {
-            writer: self.writer,
-            encoder: self.encoder,
-            notifier: self.notifier,
-        })
+        Ok(writer)
+    }
+}
+
+impl LiveFileWriter {
+    async fn write_session_event(&mut self, event: session::Event) -> io::Result<()> {
+        if let session::Event::Exit(time, status) = &event {
+            if self.exit_details {
+                let details = asciicast::Event::exit_details(*time, &status.into());
+                self.write_event(details).await?;
+            }
+        }
+
+        self.write_event(event.into()).await
+    }
+
+    async fn write_event(&mut self, event: asciicast::Event) -> io::Result<()> {
+        let mut event = event;
+
+        if let Some(segments) = self.segments.as_mut() {
+            if segments.is_due(&event) {
+                self.rotate(event.time).await?;
+            }
+
+            let segments = self.segments.as_mut().unwrap();
+            segments.feed(&event);
+            event.time = event.time.saturating_sub(segments.start_time);
+        }
+
+        let data = self.encoder.event(event);
+
+        self.write(&data).await
+    }
+
+    async fn rotate(&mut self, time: Duration) -> io::Result<()> {
+        self.finish().await?;
+
+        let segments = self.segments.as_mut().unwrap();
+        let (path, metadata) = segments.next(time);
+
+        let file = File::options()
+            .write(true)
+            .create(segments.config.overwrite)
+            .create_new(!segments.config.overwrite)
+            .truncate(segments.config.overwrite)
+            .open(&path)
+            .await?;
+
+        self.writer = file;
+        self.encoder = (segments.config.encoder)();
+        self.compressor = self.compression.map(|c| c.compressor()).transpose()?;
+        let header = self.encoder.header(&build_header(&metadata));
+        let seed = segments.vt.dump();
+        self.write(&header).await?;
+
+        // reproduce the current screen so the segment can be played on its own
+        if !seed.is_empty() {
+            let event = asciicast::Event::output(Duration::from_micros(0), seed);
+            let data = self.encoder.event(event);
+            self.write(&data).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let data = match self.compressor.as_mut() {
+            Some(compressor) => Cow::Owned(compressor.compress(data)?),
+            None => Cow::Borrowed(data),
+        };
+
+        self.writer.write_all(&data).await?;
+        self.unflushed = true;
+
+        if let Some(segments) = self.segments.as_mut() {
+            segments.size += data.len() as u64;
+        }
+
+        if self.sync_policy == SyncPolicy::Always {
+            self.sync().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn sync(&mut self) -> io::Result<()> {
+        if let Some(compressor) = self.compressor.as_mut() {
+            let data = compressor.flush()?;
+            self.writer.write_all(&data).await?;
+        }
+
+        self.writer.flush().await?;
+
+        if self.sync_policy != SyncPolicy::None {
+            self.writer.sync_data().await?;
+        }
+
+        self.unflushed = false;
+
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> io::Result<()> {
+        let data = self.encoder.flush();
+        self.write(&data).await?;
+
+        if let Some(compressor) = self.compressor.as_mut() {
+            let data = compressor.finish()?;
+            self.writer.write_all(&data).await?;
+        }
+
+        self.writer.flush().await?;
+
+        if self.sync_policy != SyncPolicy::None {
+            self.writer.sync_data().await?;
+        }
+
+        Ok(())
     }
 }
 
 #[async_trait]
 impl session::Output for LiveFileWriter {
     async fn event(&mut self, event: session::Event) -> io::Result<()> {
-        match self
-            .writer
-            .write_all(&self.encoder.event(event.into()))
-            .await
-        {
+        match self.write_session_event(event).await {
             Ok(_) => Ok(()),
 
             Err(e) => {
@@ -96,7 +285,103 @@ impl session::Output for LiveFileWriter {
     }
 
     async fn flush(&mut self) -> io::Result<()> {
-        self.writer.write_all(&self.encoder.flush()).await
+        self.finish().await
+    }
+
+    /// Writes out compressed data held by the compressor, and syncs the file
+    /// with the periodic policy, bounding the amount of data lost when the
+    /// process gets killed, also when the session is idle.
+    async fn tick(&mut self) -> io::Result<()> {
+        let sync = self.compressor.is_some() || self.sync_policy == SyncPolicy::Periodic;
+
+        if sync && self.unflushed {
+            self.sync().await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Segments {
+    fn new(config: Segmentation, metadata: Metadata) -> Self {
+        let (cols, rows) = metadata.term.size.vt_size();
+        let vt = Vt::builder().size(cols, rows).scrollback_limit(0).build();
+
+        Self {
+            config,
+            metadata,
+            number: 1,
+            start_time: Duration::from_micros(0),
+            size: 0,
+            events: 0,
+            vt,
+        }
+    }
+
+    fn is_due(&self, event: &asciicast::Event) -> bool {
+        // keep the exit event, and its details, in the last segment
+        if self.events == 0
+            || matches!(
+                event.data,
+                asciicast::EventData::Exit(_)
+                    | asciicast::EventData::Other(asciicast::EXIT_DETAILS_EVENT_CODE, _)
+            )
+        {
+            return false;
+        }
+
+        self.config
+            .max_duration
+            .is_some_and(|max| event.time.saturating_sub(self.start_time) >= max)
+            || self.config.max_size.is_some_and(|max| self.size >= max)
+    }
+
+    fn feed(&mut self, event: &asciicast::Event) {
+        match &event.data {
+            asciicast::EventData::Output(text) => {
+                self.vt.feed_str(text);
+            }
+
+            asciicast::EventData::Resize(cols, rows) => {
+                let (cols, rows) = TtySize(*cols, *rows).vt_size();
+                self.vt.resize(cols, rows);
+            }
+
+            _ => {}
+        }
+
+        self.events += 1;
+    }
+
+    fn next(&mut self, time: Duration) -> (String, Metadata) {
+        self.number += 1;
+        self.start_time = time;
+        self.size = 0;
+        self.events = 0;
+
+        let mut metadata = self.metadata.clone();
+        metadata.time += time;
+        metadata.term.size = self.vt.size().into();
+        let path = util::segment_path(&self.config.path_pattern, self.number, metadata.time);
+
+        (path, metadata)
+    }
+}
+
+fn build_header(metadata: &Metadata) -> asciicast::Header {
+    let timestamp = metadata.time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+    asciicast::Header {
+        term_cols: metadata.term.size.0,
+        term_rows: metadata.term.size.1,
+        term_type: metadata.term.type_.clone(),
+        term_version: metadata.term.version.clone(),
+        term_theme: metadata.term.theme.clone(),
+        timestamp: Some(timestamp),
+        idle_time_limit: metadata.idle_time_limit,
+        command: metadata.command.as_ref().cloned(),
+        title: metadata.title.as_ref().cloned(),
+        env: Some(metadata.env.clone()),
     }
 }
 
@@ -109,7 +394,220 @@ impl From<session::Event> for asciicast::Event {
                 asciicast::Event::resize(time, tty_size.into())
             }
             session::Event::Marker(time, label) => asciicast::Event::marker(time, label),
-            session::Event::Exit(time, status) => asciicast::Event::exit(time, status),
+            session::Event::Title(time, title) => asciicast::Event::title(time, title),
+            session::Event::Bell(time) => asciicast::Event::bell(time),
+            session::Event::Exit(time, status) => asciicast::Event::exit(time, status.status()),
+        }
+    }
+}
+
+impl From<&ExitStatus> for asciicast::ExitDetails {
+    fn from(status: &ExitStatus) -> Self {
+        asciicast::ExitDetails {
+            code: status.code,
+            signal: status.signal.map(|s| s.as_str().to_owned()),
+            core_dumped: status.core_dumped,
+            wall_time: as_secs(status.wall_time),
+            user_time: as_secs(status.user_time),
+            system_time: as_secs(status.system_time),
+            max_rss: status.max_rss,
         }
     }
 }
+
+/// Seconds with microsecond precision, same as event times.
+fn as_secs(duration: Duration) -> f64 {
+    duration.as_micros() as f64 / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{Duration, SystemTime};
+
+    use tokio::fs::File;
+
+    use super::{FileWriter, LiveFileWriter, Segmentation, SyncPolicy};
+    use crate::compression::Compression;
+    use crate::encoder::AsciicastV3Encoder;
+    use crate::notifier::NullNotifier;
+    use crate::session::{Event, Metadata, Output, TermInfo};
+    use crate::tty::TtySize;
+
+    fn metadata() -> Metadata {
+        Metadata {
+            time: SystemTime::now(),
+            term: TermInfo {
+                type_: None,
+                version: None,
+                size: TtySize(10, 2),
+                theme: None,
+            },
+            idle_time_limit: None,
+            command: None,
+            title: None,
+            env: HashMap::new(),
+        }
+    }
+
+    fn lines(path: &std::path::Path) -> Vec<serde_json::Value> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    /// Decompresses as much as possible of a gzip stream which may be incomplete.
+    fn gunzip(path: &std::path::Path) -> String {
+        use std::io::Read;
+
+        let data = std::fs::read(path).unwrap();
+        let mut decoder = flate2::read::GzDecoder::new(&data[..]);
+        let mut text = Vec::new();
+        let mut buf = [0u8; 1024];
+
+        while let Ok(n @ 1..) = decoder.read(&mut buf) {
+            text.extend_from_slice(&buf[..n]);
+        }
+
+        String::from_utf8(text).unwrap()
+    }
+
+    #[tokio::test]
+    async fn idle_compressed_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("demo.cast.gz");
+        let file = File::create(&path).await.unwrap();
+
+        let mut writer = FileWriter::new(
+            file,
+            Box::new(AsciicastV3Encoder::new(false)),
+            Box::new(NullNotifier),
+            metadata(),
+        )
+        .compressed(Compression::Gzip)
+        .start()
+        .await
+        .unwrap();
+
+        writer
+            .event(Event::Output(Duration::from_millis(0), "hello".to_owned()))
+            .await
+            .unwrap();
+
+        assert!(!gunzip(&path).contains("hello"));
+
+        writer.tick().await.unwrap();
+
+        assert!(gunzip(&path).contains("hello"));
+    }
+
+    #[tokio::test]
+    async fn idle_periodic_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = File::create(dir.path().join("demo.cast")).await.unwrap();
+
+        let mut writer = FileWriter::new(
+            file,
+            Box::new(AsciicastV3Encoder::new(false)),
+            Box::new(NullNotifier),
+            metadata(),
+        )
+        .synced(SyncPolicy::Periodic)
+        .start()
+        .await
+        .unwrap();
+
+        writer
+            .event(Event::Output(Duration::from_millis(0), "hello".to_owned()))
+            .await
+            .unwrap();
+
+        assert!(writer.unflushed);
+
+        writer.tick().await.unwrap();
+
+        assert!(!writer.unflushed);
+    }
+
+    async fn segmented_writer(dir: &std::path::Path, metadata: Metadata) -> LiveFileWriter {
+        let pattern = dir.join("demo-{n}.cast");
+        let file = File::create(dir.join("demo-1.cast")).await.unwrap();
+
+        let segmentation = Segmentation {
+            path_pattern: pattern.to_string_lossy().into_owned(),
+            max_duration: Some(Duration::from_secs(1)),
+            max_size: None,
+            overwrite: false,
+            encoder: Box::new(|| Box::new(AsciicastV3Encoder::new(false))),
+        };
+
+        FileWriter::new(
+            file,
+            Box::new(AsciicastV3Encoder::new(false)),
+            Box::new(NullNotifier),
+            metadata,
+        )
+        .segmented(segmentation)
+        .start()
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |n: usize| dir.path().join(format!("demo-{n}.cast"));
+        let mut writer = segmented_writer(dir.path(), metadata()).await;
+
+        let events = [
+            Event::Output(Duration::from_millis(0), "hello".to_owned()),
+            Event::Resize(Duration::from_millis(500), TtySize(20, 3)),
+            Event::Output(Duration::from_millis(1500), "!".to_owned()),
+        ];
+
+        for event in events {
+            writer.event(event).await.unwrap();
+        }
+
+        writer.flush().await.unwrap();
+
+        let first = lines(&path(1));
+
+        assert_eq!(first.len(), 3);
+        assert_eq!(first[0]["term"]["cols"], 10);
+
+        let second = lines(&path(2));
+
+        assert_eq!(second.len(), 3);
+        assert_eq!(second[0]["term"]["cols"], 20);
+        assert_eq!(second[0]["term"]["rows"], 3);
+        assert_eq!(second[1][0], 0.0);
+        assert_eq!(second[1][1], "o");
+        assert!(second[1][2].as_str().unwrap().contains("hello"));
+        assert_eq!(second[2], serde_json::json!([0.0, "o", "!"]));
+    }
+
+    #[tokio::test]
+    async fn segments_with_unknown_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut metadata = metadata();
+        metadata.term.size = TtySize(0, 0);
+        let mut writer = segmented_writer(dir.path(), metadata).await;
+
+        let events = [
+            Event::Output(Duration::from_millis(0), "hello".to_owned()),
+            Event::Resize(Duration::from_millis(500), TtySize(0, 0)),
+            Event::Output(Duration::from_millis(1500), "!".to_owned()),
+        ];
+
+        for event in events {
+            writer.event(event).await.unwrap();
+        }
+
+        writer.flush().await.unwrap();
+
+        assert_eq!(lines(&dir.path().join("demo-2.cast")).len(), 3);
+    }
+}
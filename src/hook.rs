@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+
+use anyhow::{bail, Result};
+use tokio::process::Command;
+
+/// Environment passed to session hooks.
+pub struct HookEnv {
+    pub session_id: String,
+    pub output_paths: Vec<String>,
+    pub stream_url: Option<String>,
+    /// URLs of the recordings uploaded at the end of the session, set for the
+    /// post hook, which runs after the upload.
+    pub upload_urls: Vec<String>,
+    pub exit_status: Option<i32>,
+}
+
+impl HookEnv {
+    fn vars(&self) -> HashMap<&'static str, String> {
+        let mut vars = HashMap::new();
+
+        vars.insert("ASCIINEMA_SESSION", self.session_id.clone());
+
+        if !self.output_paths.is_empty() {
+            vars.insert("ASCIINEMA_OUTPUT_PATH", self.output_paths.join("\n"));
+        }
+
+        if let Some(url) = &self.stream_url {
+            vars.insert("ASCIINEMA_STREAM_URL", url.clone());
+        }
+
+        if !self.upload_urls.is_empty() {
+            vars.insert("ASCIINEMA_UPLOAD_URL", self.upload_urls.join("\n"));
+        }
+
+        if let Some(status) = self.exit_status {
+            vars.insert("ASCIINEMA_EXIT_STATUS", status.to_string());
+        }
+
+        vars
+    }
+}
+
+/// Runs a hook command with the shell, waiting for it to finish.
+///
+/// The hook inherits stdout and stderr, so it can report progress, while its
+/// stdin is closed.
+pub async fn run(command: &str, env: &HookEnv) -> Result<()> {
+    let status = Command::new("/bin/sh")
+        .args(["-c", command])
+        .envs(env.vars())
+        .stdin(Stdio::null())
+        .status()
+        .await?;
+
+    if !status.success() {
+        match status.code() {
+            Some(code) => bail!("exit status: {code}"),
+            None => bail!("terminated by a signal"),
+        }
+    }
+
+    Ok(())
+}
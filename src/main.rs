@@ -1,24 +1,33 @@
 mod alis;
+mod ansi;
 mod api;
 mod asciicast;
 mod cli;
 mod cmd;
+mod command_markers;
+mod compression;
 mod config;
+mod control;
 mod encoder;
 mod fd;
 mod file_writer;
 mod forwarder;
 mod hash;
+mod hook;
 mod html;
 mod leb128;
 mod locale;
+mod marker_prompt;
 mod notifier;
 mod player;
 mod pty;
+mod redaction;
+mod script;
 mod server;
 mod session;
 mod status;
 mod stream;
+mod title_bell;
 mod tty;
 mod util;
 
@@ -26,7 +35,7 @@ use std::process::{ExitCode, Termination};
 
 use clap::Parser;
 
-use self::cli::{Cli, Commands, Session};
+use self::cli::{Cli, Commands, OutputFile, Session};
 
 fn main() -> ExitCode {
     let cli = Cli::parse();
@@ -39,20 +48,39 @@ fn main() -> ExitCode {
 
     crate::config::check_legacy_config_file();
 
+    let profile = cli.profile.as_deref();
+
     match cli.command {
         Commands::Record(cmd) => {
             let cmd = Session {
-                output_file: Some(cmd.file),
+                output_file: vec![OutputFile {
+                    path: cmd.file,
+                    format: None,
+                }],
                 capture_input: cmd.capture_input,
                 append: cmd.append,
                 output_format: cmd.output_format,
                 overwrite: cmd.overwrite,
+                segment_duration: cmd.segment_duration,
+                segment_size: cmd.segment_size,
                 command: cmd.command,
+                args: cmd.args,
                 capture_env: cmd.capture_env,
+                cwd: cmd.cwd,
+                set_env: cmd.set_env,
+                unset_env: cmd.unset_env,
+                clean_env: cmd.clean_env,
+                umask: cmd.umask,
+                max_duration: cmd.max_duration,
+                max_size: cmd.max_size,
+                stop_after_idle: cmd.stop_after_idle,
                 title: cmd.title,
                 idle_time_limit: cmd.idle_time_limit,
                 headless: cmd.headless,
                 window_size: cmd.window_size,
+                script: cmd.script,
+                upload: cmd.upload,
+                upload_and_delete: cmd.upload_and_delete,
                 stream_local: None,
                 stream_remote: None,
                 return_: cmd.return_,
@@ -61,22 +89,36 @@ fn main() -> ExitCode {
                 env: vec!["ASCIINEMA_REC=1".to_owned()],
             };
 
-            cmd.run().report()
+            cmd.run(profile).report()
         }
 
         Commands::Stream(cmd) => {
             let cmd = Session {
-                output_file: None,
+                output_file: Vec::new(),
                 capture_input: cmd.capture_input,
                 append: false,
                 output_format: None,
                 overwrite: false,
+                segment_duration: None,
+                segment_size: None,
                 command: cmd.command,
+                args: cmd.args,
                 capture_env: cmd.capture_env,
+                cwd: cmd.cwd,
+                set_env: cmd.set_env,
+                unset_env: cmd.unset_env,
+                clean_env: cmd.clean_env,
+                umask: cmd.umask,
+                max_duration: cmd.max_duration,
+                max_size: cmd.max_size,
+                stop_after_idle: cmd.stop_after_idle,
                 title: cmd.title,
                 idle_time_limit: None,
                 headless: cmd.headless,
                 window_size: cmd.window_size,
+                script: None,
+                upload: false,
+                upload_and_delete: false,
                 stream_local: cmd.local,
                 stream_remote: cmd.remote,
                 return_: cmd.return_,
@@ -85,14 +127,16 @@ fn main() -> ExitCode {
                 env: Vec::new(),
             };
 
-            cmd.run().report()
+            cmd.run(profile).report()
         }
 
-        Commands::Session(cmd) => cmd.run().report(),
-        Commands::Play(cmd) => cmd.run().report(),
+        Commands::Session(cmd) => cmd.run(profile).report(),
+        Commands::Play(cmd) => cmd.run(profile).report(),
         Commands::Cat(cmd) => cmd.run().report(),
         Commands::Convert(cmd) => cmd.run().report(),
-        Commands::Upload(cmd) => cmd.run().report(),
-        Commands::Auth(cmd) => cmd.run().report(),
+        Commands::Repair(cmd) => cmd.run().report(),
+        Commands::Upload(cmd) => cmd.run(profile).report(),
+        Commands::Auth(cmd) => cmd.run(profile).report(),
+        Commands::Ctl(cmd) => cmd.run().report(),
     }
 }
@@ -0,0 +1,293 @@
+use std::fmt::Write;
+
+use avt::{Color, Line, Pen, Vt};
+use bytes::BytesMut;
+
+use crate::tty::TtySize;
+use crate::util::Utf8Decoder;
+
+/// Marker label prompt drawn over the bottom row of the terminal.
+///
+/// The session output is fed to a virtual terminal, so the row covered by the
+/// prompt can be repainted from its contents once the prompt is closed, and
+/// the prompt is redrawn only when the application writes over it.
+pub struct MarkerPrompt {
+    label: Option<String>,
+    visible: bool,
+    vt: Vt,
+    decoder: Utf8Decoder,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Submit(String),
+    Cancel,
+}
+
+impl MarkerPrompt {
+    pub fn new(size: TtySize) -> Self {
+        let (cols, rows) = size.vt_size();
+
+        Self {
+            label: None,
+            visible: false,
+            vt: Vt::builder().size(cols, rows).scrollback_limit(0).build(),
+            decoder: Utf8Decoder::new(),
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.label = Some(String::new());
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.label.is_some()
+    }
+
+    pub fn input(&mut self, data: &[u8]) -> Option<Action> {
+        // a lone Esc cancels the prompt, other escape sequences (e.g. arrow keys) are ignored
+        if data.first() == Some(&0x1b) {
+            if data.len() == 1 {
+                self.label = None;
+                return Some(Action::Cancel);
+            }
+
+            return None;
+        }
+
+        let label = self.label.as_mut()?;
+
+        for c in String::from_utf8_lossy(data).chars() {
+            match c {
+                '\r' | '\n' => {
+                    let label = label.trim().to_owned();
+                    self.label = None;
+
+                    return Some(Action::Submit(label));
+                }
+
+                // ^C
+                '\x03' => {
+                    self.label = None;
+
+                    return Some(Action::Cancel);
+                }
+
+                // Backspace
+                '\x7f' | '\x08' => {
+                    label.pop();
+                }
+
+                c if !c.is_control() => {
+                    label.push(c);
+                }
+
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    /// Tracks the session output, redrawing the prompt when it got overwritten.
+    pub fn output(&mut self, data: &[u8], output: &mut BytesMut) {
+        let text = self.decoder.feed(data);
+        let rows = self.vt.size().1;
+        let changes = self.vt.feed_str(&text);
+
+        if !self.visible || !changes.lines.contains(&(rows - 1)) {
+            return;
+        }
+
+        // when the screen scrolled, the prompt moved up together with it
+        let scrolled = rows > 1 && changes.lines.contains(&(rows - 2));
+        drop(changes);
+
+        if scrolled {
+            self.restore_line(rows - 2, output);
+        }
+
+        self.render(output);
+    }
+
+    /// Draws the prompt, or repaints the row below it once it got closed.
+    pub fn render(&mut self, output: &mut BytesMut) {
+        let rows = self.vt.size().1;
+
+        match &self.label {
+            Some(label) => {
+                // save cursor, go to the bottom row, print the prompt, restore cursor
+                let prompt =
+                    format!("\x1b7\x1b[{rows};1H\x1b[0;7m Marker label: {label}\x1b[0m\x1b[K\x1b8");
+
+                output.extend_from_slice(prompt.as_bytes());
+                self.visible = true;
+            }
+
+            None if self.visible => {
+                self.restore_line(rows - 1, output);
+                self.visible = false;
+            }
+
+            None => {}
+        }
+    }
+
+    pub fn resize(&mut self, size: TtySize) {
+        let (cols, rows) = size.vt_size();
+        self.vt.resize(cols, rows);
+    }
+
+    fn restore_line(&self, row: usize, output: &mut BytesMut) {
+        let line = render_line(self.vt.line(row));
+        let restore = format!("\x1b7\x1b[{};1H{line}\x1b[0m\x1b8", row + 1);
+        output.extend_from_slice(restore.as_bytes());
+    }
+}
+
+fn render_line(line: &Line) -> String {
+    let mut text = String::new();
+
+    for chunk in line.chunks(|c1, c2| c1.pen() != c2.pen()) {
+        text.push_str(&sgr(chunk[0].pen()));
+        text.extend(chunk.iter().map(|cell| cell.char()));
+    }
+
+    text
+}
+
+fn sgr(pen: &Pen) -> String {
+    let mut params = String::from("\x1b[0");
+
+    if let Some(color) = pen.foreground() {
+        push_color(&mut params, color, 30);
+    }
+
+    if let Some(color) = pen.background() {
+        push_color(&mut params, color, 40);
+    }
+
+    let attrs = [
+        (pen.is_bold(), "1"),
+        (pen.is_faint(), "2"),
+        (pen.is_italic(), "3"),
+        (pen.is_underline(), "4"),
+        (pen.is_blink(), "5"),
+        (pen.is_inverse(), "7"),
+        (pen.is_strikethrough(), "9"),
+    ];
+
+    for (_, param) in attrs.iter().filter(|(set, _)| *set) {
+        params.push(';');
+        params.push_str(param);
+    }
+
+    params.push('m');
+
+    params
+}
+
+fn push_color(params: &mut String, color: Color, base: u8) {
+    let _ = match color {
+        Color::Indexed(c) if c < 8 => write!(params, ";{}", base + c),
+        Color::Indexed(c) if c < 16 => write!(params, ";{}", base + 52 + c),
+        Color::Indexed(c) => write!(params, ";{};5;{c}", base + 8),
+        Color::RGB(c) => write!(params, ";{};2;{};{};{}", base + 8, c.r, c.g, c.b),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::{Action, MarkerPrompt};
+    use crate::tty::TtySize;
+
+    fn prompt() -> MarkerPrompt {
+        let mut prompt = MarkerPrompt::new(TtySize(20, 3));
+        prompt.open();
+
+        prompt
+    }
+
+    #[test]
+    fn typing() {
+        let mut prompt = prompt();
+
+        assert_eq!(prompt.input(b"fo"), None);
+        assert_eq!(prompt.input("ó".as_bytes()), None);
+        assert_eq!(prompt.input(b"\x01"), None);
+        assert_eq!(prompt.input(b"\x1b[A"), None);
+        assert!(prompt.is_open());
+        assert_eq!(prompt.label.as_deref(), Some("foó"));
+    }
+
+    #[test]
+    fn backspace() {
+        let mut prompt = prompt();
+
+        prompt.input(b"foo\x7f");
+        assert_eq!(prompt.label.as_deref(), Some("fo"));
+
+        prompt.input(b"\x08\x08\x08");
+        assert_eq!(prompt.label.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn enter() {
+        let mut prompt = prompt();
+
+        prompt.input(b" build");
+        assert_eq!(
+            prompt.input(b" \r"),
+            Some(Action::Submit("build".to_owned()))
+        );
+        assert!(!prompt.is_open());
+
+        let mut prompt = self::prompt();
+
+        assert_eq!(prompt.input(b"a\nb"), Some(Action::Submit("a".to_owned())));
+    }
+
+    #[test]
+    fn escape() {
+        let mut prompt = prompt();
+
+        prompt.input(b"foo");
+        assert_eq!(prompt.input(b"\x1b"), Some(Action::Cancel));
+        assert!(!prompt.is_open());
+
+        let mut prompt = self::prompt();
+
+        assert_eq!(prompt.input(b"foo\x03"), Some(Action::Cancel));
+        assert!(!prompt.is_open());
+    }
+
+    #[test]
+    fn restore_bottom_row() {
+        let mut prompt = prompt();
+        let mut output = BytesMut::new();
+
+        prompt.output(b"\x1b[3;1H\x1b[1;31m-- INSERT --", &mut output);
+        assert!(output.is_empty());
+
+        prompt.render(&mut output);
+        output.clear();
+
+        // output elsewhere doesn't redraw the prompt
+        prompt.output(b"\x1b[1;1Hfoo", &mut output);
+        assert!(output.is_empty());
+
+        prompt.output(b"\x1b[3;1H\x1b[0m-- VISUAL --", &mut output);
+        assert!(String::from_utf8_lossy(&output).contains("Marker label:"));
+        output.clear();
+
+        prompt.input(b"\x1b");
+        prompt.render(&mut output);
+
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "\x1b7\x1b[3;1H\x1b[0m-- VISUAL --        \x1b[0m\x1b8"
+        );
+    }
+}
@@ -1,16 +1,43 @@
-use anyhow::Result;
-use tokio::sync::mpsc;
+use anyhow::{bail, Result};
+use avt::Vt;
+use tokio::sync::mpsc::{self, error::TryRecvError};
 use tokio::time::{self, Duration, Instant};
 
-use crate::asciicast::{self, Event, EventData};
+use crate::asciicast::{self, Event, EventData, TITLE_EVENT_CODE};
 use crate::config::Key;
 use crate::tty::{DevTty, RawTty};
 
+/// Opens the recording again, so seeking can go back to events which are no
+/// longer kept in memory.
+pub type Reopen = dyn Fn() -> Result<asciicast::Asciicast<'static>>;
+
+const SEEK_STEP: Duration = Duration::from_secs(5);
+const SEEK_LONG_STEP: Duration = Duration::from_secs(30);
+const KEYFRAME_INTERVAL: Duration = Duration::from_secs(30);
+const STATUS_INTERVAL: Duration = Duration::from_millis(250);
+const FLASH_DURATION: Duration = Duration::from_secs(1);
+const MIN_SPEED: f64 = 1.0 / 64.0;
+const MAX_SPEED: f64 = 64.0;
+
+/// Brings the terminal to its initial state before painting a screen dump:
+/// switches to the primary screen, soft-resets it and clears it.
+const RESET_SEQ: &str = "\x1b[?1049l\x1b[!p\x1b[H\x1b[2J";
+
 pub struct KeyBindings {
     pub quit: Key,
     pub pause: Key,
     pub step: Key,
     pub next_marker: Key,
+    pub prev_marker: Key,
+    pub seek_backward: Key,
+    pub seek_forward: Key,
+    pub seek_backward_long: Key,
+    pub seek_forward_long: Key,
+    pub status: Key,
+    pub speed_up: Key,
+    pub slow_down: Key,
+    pub reset_speed: Key,
+    pub idle_time_limit: Key,
 }
 
 impl Default for KeyBindings {
@@ -20,148 +47,920 @@ impl Default for KeyBindings {
             pause: Some(vec![b' ']),
             step: Some(vec![b'.']),
             next_marker: Some(vec![b']']),
+            prev_marker: Some(vec![b'[']),
+            seek_backward: Some(b"\x1b[D".to_vec()),
+            seek_forward: Some(b"\x1b[C".to_vec()),
+            seek_backward_long: Some(b"\x1b[1;2D".to_vec()),
+            seek_forward_long: Some(b"\x1b[1;2C".to_vec()),
+            status: Some(vec![b's']),
+            speed_up: Some(vec![b'+']),
+            slow_down: Some(vec![b'-']),
+            reset_speed: Some(vec![b'=']),
+            idle_time_limit: Some(vec![b'i']),
         }
     }
 }
 
+/// Part of the recording to play, bounded by times or marker labels.
+#[derive(Default)]
+pub struct Range {
+    pub start: Option<Bound>,
+    pub end: Option<Bound>,
+}
+
+pub enum Bound {
+    Time(Duration),
+    Marker(String),
+}
+
+/// Playback settings, see `cli::Play`.
+pub struct Options {
+    pub speed: f64,
+    pub idle_time_limit: Option<Duration>,
+    /// Total playback time, in the original timeline and with the idle time
+    /// limit applied, when known before playback.
+    pub duration: Option<(Duration, Duration)>,
+    pub pause_on_markers: bool,
+    pub auto_resize: bool,
+    pub range: Range,
+    pub follow: bool,
+    pub keys: KeyBindings,
+}
+
 pub async fn play(
     recording: asciicast::Asciicast<'static>,
-    speed: f64,
-    idle_time_limit_override: Option<f64>,
-    pause_on_markers: bool,
-    keys: &KeyBindings,
-    auto_resize: bool,
+    reopen: Option<&Reopen>,
+    options: &Options,
 ) -> Result<bool> {
-    let initial_cols = recording.header.term_cols;
-    let initial_rows = recording.header.term_rows;
-    let mut events = emit_session_events(recording, speed, idle_time_limit_override)?;
-    let mut epoch = Instant::now();
-    let mut pause_elapsed_time: Option<u64> = None;
-    let mut next_event = events.recv().await.transpose()?;
+    let size = (
+        recording.header.term_cols as usize,
+        recording.header.term_rows as usize,
+    );
+
+    let idle_time_limit = options
+        .idle_time_limit
+        .or(recording.header.get_idle_time_limit());
+
+    let duration = options
+        .duration
+        .map(|(time, limited_time)| Timestamp { time, limited_time });
+
+    let events = emit_session_events(recording)?;
+    let tty = DevTty::open().await?;
+
+    if options.auto_resize {
+        tty.resize(size.into()).await?;
+    }
+
+    // save the window title, which the recording may change, and restore it afterwards
+    tty.write_all(b"\x1b[22;2t").await?;
+    let player = Player::new(
+        &tty,
+        events,
+        reopen,
+        size,
+        idle_time_limit,
+        duration,
+        options.auto_resize,
+    );
+
+    let result = play_events(player, options).await;
+    tty.write_all(b"\x1b[23;2t").await?;
+
+    result
+}
+
+async fn play_events<T: RawTty>(mut player: Player<'_, T>, options: &Options) -> Result<bool> {
+    let Options { keys, range, .. } = options;
+    let mut clock = Clock::new(options.speed);
     let mut input = [0u8; 1024];
-    let mut tty = DevTty::open().await?;
 
-    if auto_resize {
-        tty.resize((initial_cols as usize, initial_rows as usize).into())
-            .await?;
+    if let Some(start) = player.resolve(range.start.as_ref()).await? {
+        player.seek(start).await?;
+        clock.set(start);
     }
 
-    while let Some(Event { time, data }) = &next_event {
-        if let Some(pet) = pause_elapsed_time {
-            let n = tty.read(&mut input).await?;
-            let key = &input[..n];
+    // the end time stays on the timeline it was given on, even when idle time
+    // limiting gets toggled later, while the end marker is looked for during
+    // the playback, without reading ahead to it
+    let end_idle_limited = player.idle_limited;
 
-            if keys.quit.as_ref().is_some_and(|k| k == key) {
-                tty.write_all("\r\n".as_bytes()).await?;
-                return Ok(false);
-            }
+    // from now on, seeking in a followed file stops at its current end instead
+    // of waiting for the file to grow
+    player.live = options.follow;
 
-            if keys.pause.as_ref().is_some_and(|k| k == key) {
-                epoch = Instant::now() - Duration::from_micros(pet);
-                pause_elapsed_time = None;
-            } else if keys.step.as_ref().is_some_and(|k| k == key) {
-                pause_elapsed_time = Some(time.as_micros() as u64);
+    loop {
+        player.receive_available();
 
-                match data {
-                    EventData::Output(data) => {
-                        tty.write_all(data.as_bytes()).await?;
-                    }
+        // `None` while waiting for the recording to continue, e.g. for the
+        // followed file to grow
+        let time = if player.position < player.len() {
+            Some(player.time_at(player.position))
+        } else if let Some(error) = player.error.take() {
+            return Err(error);
+        } else if player.complete {
+            break;
+        } else {
+            None
+        };
+
+        if time.is_some() && player.is_at_end(range.end.as_ref(), end_idle_limited) {
+            break;
+        }
+
+        player.expire_flash().await?;
+        player.update_status(&clock).await?;
 
-                    EventData::Resize(cols, rows) if auto_resize => {
-                        tty.resize((*cols as usize, *rows as usize).into()).await?;
+        let mut timeout = None;
+
+        if !clock.is_paused() {
+            if let Some(time) = time {
+                let delay = clock.until(time);
+
+                if delay.is_zero() {
+                    if player.advance(true).await? && options.pause_on_markers {
+                        clock.pause_at(time);
                     }
 
-                    _ => {}
+                    continue;
                 }
 
-                next_event = events.recv().await.transpose()?;
-            } else if keys.next_marker.as_ref().is_some_and(|k| k == key) {
-                while let Some(Event { time, data }) = next_event {
-                    next_event = events.recv().await.transpose()?;
+                timeout = Some(delay);
+            }
 
-                    match data {
-                        EventData::Output(data) => {
-                            tty.write_all(data.as_bytes()).await?;
-                        }
+            if player.status.is_some() {
+                // wake up periodically to keep the elapsed time in the status line current
+                timeout = min_timeout(timeout, STATUS_INTERVAL);
+            }
+        }
 
-                        EventData::Marker(_) => {
-                            pause_elapsed_time = Some(time.as_micros() as u64);
-                            break;
-                        }
+        if let Some(deadline) = player.flash_until {
+            timeout = min_timeout(timeout, deadline.saturating_duration_since(Instant::now()));
+        }
 
-                        EventData::Resize(cols, rows) if auto_resize => {
-                            tty.resize((cols as usize, rows as usize).into()).await?;
-                        }
+        let n = tokio::select! {
+            event = player.source.recv(), if time.is_none() => {
+                player.accept(event);
+                continue;
+            }
 
-                        _ => {}
-                    }
+            _ = time::sleep(timeout.unwrap_or_default()), if timeout.is_some() => continue,
+
+            result = player.tty.read(&mut input) => result?,
+        };
+
+        let key = normalize_key(&input[..n]);
+        let matches = |binding: &Key| binding.as_ref().is_some_and(|k| *k == key);
+
+        if matches(&keys.quit) {
+            player.tty.write_all("\r\n".as_bytes()).await?;
+            return Ok(false);
+        }
+
+        if matches(&keys.pause) {
+            if clock.is_paused() {
+                clock.resume();
+            } else {
+                clock.pause();
+            }
+        } else if matches(&keys.step) && clock.is_paused() {
+            if let Some(time) = time {
+                clock.set(time);
+                player.advance(true).await?;
+            }
+        } else if matches(&keys.status) {
+            player.toggle_status().await?;
+        } else if matches(&keys.speed_up) {
+            let speed = clock.speed * 2.0;
+            player.change_speed(&mut clock, speed).await?;
+        } else if matches(&keys.slow_down) {
+            let speed = clock.speed / 2.0;
+            player.change_speed(&mut clock, speed).await?;
+        } else if matches(&keys.reset_speed) {
+            player.change_speed(&mut clock, options.speed).await?;
+        } else if matches(&keys.idle_time_limit) {
+            player.toggle_idle_time_limit(&mut clock).await?;
+        } else {
+            player.handle_seek_key(&key, keys, &mut clock).await?;
+        }
+    }
+
+    Ok(true)
+}
+
+fn min_timeout(timeout: Option<Duration>, other: Duration) -> Option<Duration> {
+    Some(timeout.map_or(other, |timeout| timeout.min(other)))
+}
+
+/// Playback position in the recording, advancing with wall clock time
+/// multiplied by the speed, unless paused.
+struct Clock {
+    base: Duration,
+    epoch: Instant,
+    speed: f64,
+    paused: bool,
+}
+
+impl Clock {
+    fn new(speed: f64) -> Self {
+        Self {
+            base: Duration::ZERO,
+            epoch: Instant::now(),
+            speed,
+            paused: false,
+        }
+    }
+
+    fn time(&self) -> Duration {
+        if self.paused {
+            self.base
+        } else {
+            self.base + self.epoch.elapsed().mul_f64(self.speed)
+        }
+    }
+
+    /// Returns the wall clock time left until the clock reaches `time`.
+    fn until(&self, time: Duration) -> Duration {
+        time.saturating_sub(self.time()).div_f64(self.speed)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn pause(&mut self) {
+        self.base = self.time();
+        self.paused = true;
+    }
+
+    fn pause_at(&mut self, time: Duration) {
+        self.base = time;
+        self.paused = true;
+    }
+
+    fn resume(&mut self) {
+        self.epoch = Instant::now();
+        self.paused = false;
+    }
+
+    fn set(&mut self, time: Duration) {
+        self.base = time;
+        self.epoch = Instant::now();
+    }
+
+    fn set_speed(&mut self, speed: f64) {
+        self.set(self.time());
+        self.speed = speed;
+    }
+}
+
+/// Plays events on the tty while keeping the resulting terminal state in a
+/// virtual terminal, so that the screen can be rebuilt after seeking.
+///
+/// Every `KEYFRAME_INTERVAL` a dump of the virtual terminal is saved, so
+/// seeking only needs to replay the events following the closest keyframe.
+/// Events are kept in memory, together with their times with the idle time
+/// limit applied, from the keyframe preceding the one the playback is past.
+/// Seeking further back reads the recording again from the start, skipping
+/// to the keyframe, or, when it can't be read again (standard input), stops at
+/// the oldest kept keyframe.
+struct Player<'a, T> {
+    tty: &'a T,
+    source: mpsc::Receiver<Result<Event>>,
+    reopen: Option<&'a Reopen>,
+    complete: bool,
+    live: bool,
+    error: Option<anyhow::Error>,
+    /// Received events, starting with the one at `offset`.
+    events: Vec<Event>,
+    limited_times: Vec<Duration>,
+    offset: usize,
+    /// Times of the event preceding `offset`.
+    base: Timestamp,
+    /// Times of the last event, when known ahead.
+    total: Option<Timestamp>,
+    idle_time_limit: Option<Duration>,
+    idle_limited: bool,
+    position: usize,
+    vt: Vt,
+    title: Option<String>,
+    keyframes: Vec<Keyframe>,
+    markers: Vec<Marker>,
+    auto_resize: bool,
+    marker: Option<String>,
+    status: Option<String>,
+    flash_until: Option<Instant>,
+}
+
+/// Time of an event, in the original timeline and with the idle time limit
+/// applied.
+#[derive(Clone, Copy, Default)]
+struct Timestamp {
+    time: Duration,
+    limited_time: Duration,
+}
+
+struct Keyframe {
+    position: usize,
+    /// Time of the event preceding the keyframe.
+    timestamp: Timestamp,
+    size: (usize, usize),
+    title: Option<String>,
+    dump: String,
+}
+
+/// Marker seen in the recording, kept also when its event is no longer in
+/// memory, for going back to it.
+struct Marker {
+    position: usize,
+    timestamp: Timestamp,
+    label: String,
+}
+
+impl Timestamp {
+    fn get(&self, idle_limited: bool) -> Duration {
+        if idle_limited {
+            self.limited_time
+        } else {
+            self.time
+        }
+    }
+}
+
+impl<'a, T: RawTty> Player<'a, T> {
+    fn new(
+        tty: &'a T,
+        source: mpsc::Receiver<Result<Event>>,
+        reopen: Option<&'a Reopen>,
+        size: (usize, usize),
+        idle_time_limit: Option<Duration>,
+        total: Option<Timestamp>,
+        auto_resize: bool,
+    ) -> Self {
+        let vt = new_vt(size);
+
+        let keyframe = Keyframe {
+            position: 0,
+            timestamp: Timestamp::default(),
+            size,
+            title: None,
+            dump: vt.dump(),
+        };
+
+        Self {
+            tty,
+            source,
+            reopen,
+            complete: false,
+            live: false,
+            error: None,
+            events: Vec::new(),
+            limited_times: Vec::new(),
+            offset: 0,
+            base: Timestamp::default(),
+            total,
+            idle_time_limit,
+            idle_limited: idle_time_limit.is_some(),
+            position: 0,
+            vt,
+            title: None,
+            keyframes: vec![keyframe],
+            markers: Vec::new(),
+            auto_resize,
+            marker: None,
+            status: None,
+            flash_until: None,
+        }
+    }
+
+    /// Returns the number of events received so far.
+    fn len(&self) -> usize {
+        self.offset + self.events.len()
+    }
+
+    fn event(&self, index: usize) -> &Event {
+        &self.events[index - self.offset]
+    }
+
+    fn timestamp(&self, index: usize) -> Timestamp {
+        Timestamp {
+            time: self.event(index).time,
+            limited_time: self.limited_times[index - self.offset],
+        }
+    }
+
+    /// Returns the time of the next event, or `None` at the end of the recording.
+    async fn next_time(&mut self) -> Result<Option<Duration>> {
+        if self.position == self.len() && !self.receive().await? {
+            return Ok(None);
+        }
+
+        Ok(Some(self.time_at(self.position)))
+    }
+
+    fn time_at(&self, index: usize) -> Duration {
+        self.event_time(index, self.idle_limited)
+    }
+
+    fn event_time(&self, index: usize, idle_limited: bool) -> Duration {
+        self.timestamp(index).get(idle_limited)
+    }
+
+    /// Returns the time of the last event played before `position`.
+    fn time_before(&self, position: usize) -> Duration {
+        if position > self.offset {
+            self.time_at(position - 1)
+        } else {
+            self.base.get(self.idle_limited)
+        }
+    }
+
+    /// Returns the total playback time, known ahead or once all events were
+    /// received.
+    fn duration(&self) -> Option<Duration> {
+        if self.complete {
+            Some(self.time_before(self.len()))
+        } else {
+            self.total.map(|total| total.get(self.idle_limited))
+        }
+    }
+
+    /// Checks whether the next event is past the end of the played range.
+    fn is_at_end(&self, end: Option<&Bound>, idle_limited: bool) -> bool {
+        match end {
+            Some(Bound::Time(end)) => self.event_time(self.position, idle_limited) > *end,
+
+            Some(Bound::Marker(label)) => {
+                matches!(&self.event(self.position).data, EventData::Marker(l) if l == label)
+            }
+
+            None => false,
+        }
+    }
+
+    /// Receives more events, returning false at the end of the recording. Once
+    /// live, only the events which are ready are taken, without waiting.
+    async fn receive(&mut self) -> Result<bool> {
+        let len = self.len();
+
+        if self.live {
+            self.receive_available();
+        } else if self.error.is_none() {
+            let event = self.source.recv().await;
+            self.accept(event);
+        }
+
+        if self.len() > len {
+            return Ok(true);
+        }
+
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(false),
+        }
+    }
+
+    /// Takes the events which are ready without waiting. A read error is kept
+    /// until the playback gets to it.
+    fn receive_available(&mut self) {
+        while self.error.is_none() && !self.complete {
+            match self.source.try_recv() {
+                Ok(event) => self.accept(Some(event)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => self.accept(None),
+            }
+        }
+    }
+
+    fn accept(&mut self, event: Option<Result<Event>>) {
+        match event {
+            Some(Ok(event)) => self.push(event),
+            Some(Err(error)) => self.error = Some(error),
+            None => self.complete = true,
+        }
+    }
+
+    fn push(&mut self, event: Event) {
+        let prev = match self.len() {
+            len if len > self.offset => self.timestamp(len - 1),
+            _ => self.base,
+        };
+
+        let delay = event.time.saturating_sub(prev.time);
+        let delay = self.idle_time_limit.map_or(delay, |limit| delay.min(limit));
+
+        let timestamp = Timestamp {
+            time: event.time,
+            limited_time: prev.limited_time + delay,
+        };
+
+        // markers are received again when the recording is read again
+        if let EventData::Marker(label) = &event.data {
+            let position = self.len();
+
+            if self.markers.last().is_none_or(|m| m.position < position) {
+                self.markers.push(Marker {
+                    position,
+                    timestamp,
+                    label: label.clone(),
+                });
+            }
+        }
+
+        self.limited_times.push(timestamp.limited_time);
+        self.events.push(event);
+    }
+
+    /// Plays the next event, returning whether it was a marker. When not
+    /// `visible` only the virtual terminal is updated.
+    async fn advance(&mut self, visible: bool) -> Result<bool> {
+        let Event { time, data } = &self.events[self.position - self.offset];
+        let time = *time;
+        self.position += 1;
+
+        match data {
+            EventData::Output(data) => {
+                self.vt.feed_str(data);
+
+                if visible {
+                    self.tty.write_all(data.as_bytes()).await?;
+                }
+            }
+
+            EventData::Resize(cols, rows) => {
+                self.vt.resize(*cols as usize, *rows as usize);
+
+                if visible && self.auto_resize {
+                    self.tty
+                        .resize((*cols as usize, *rows as usize).into())
+                        .await?;
+                }
+            }
+
+            EventData::Other(TITLE_EVENT_CODE, title) => {
+                self.title = Some(title.clone());
+
+                if visible && self.status.is_none() {
+                    set_title(self.tty, title).await?;
                 }
             }
+
+            EventData::Marker(label) => {
+                self.marker = Some(label.clone());
+
+                return Ok(true);
+            }
+
+            _ => {}
+        }
+
+        // keyframes are spaced on the original timeline, which doesn't change
+        let last = self.keyframes.last().unwrap();
+
+        if self.position > last.position && time >= last.timestamp.time + KEYFRAME_INTERVAL {
+            self.keyframes.push(Keyframe {
+                position: self.position,
+                timestamp: self.timestamp(self.position - 1),
+                size: self.vt.size(),
+                title: self.title.clone(),
+                dump: self.vt.dump(),
+            });
+
+            self.trim();
+        }
+
+        Ok(false)
+    }
+
+    /// Drops the events preceding the keyframe before the one the playback
+    /// is past.
+    fn trim(&mut self) {
+        let passed = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.position <= self.position);
+
+        let cutoff = self.keyframes[passed.saturating_sub(2)].position;
+
+        if cutoff > self.offset {
+            self.base = self.timestamp(cutoff - 1);
+            self.events.drain(..cutoff - self.offset);
+            self.limited_times.drain(..cutoff - self.offset);
+            self.offset = cutoff;
+        }
+    }
+
+    async fn handle_seek_key(
+        &mut self,
+        key: &[u8],
+        keys: &KeyBindings,
+        clock: &mut Clock,
+    ) -> Result<()> {
+        let matches = |binding: &Key| binding.as_ref().is_some_and(|k| k == key);
+        let time = clock.time();
+
+        let target = if matches(&keys.seek_backward) {
+            time.saturating_sub(SEEK_STEP)
+        } else if matches(&keys.seek_forward) {
+            time + SEEK_STEP
+        } else if matches(&keys.seek_backward_long) {
+            time.saturating_sub(SEEK_LONG_STEP)
+        } else if matches(&keys.seek_forward_long) {
+            time + SEEK_LONG_STEP
+        } else if matches(&keys.prev_marker) {
+            self.prev_marker_time(time).unwrap_or_default()
+        } else if matches(&keys.next_marker) {
+            match self.find_marker(|_| true).await? {
+                Some(index) => self.time_at(index),
+                None => return Ok(()),
+            }
         } else {
-            while let Some(Event { time, data }) = &next_event {
-                let delay = time.as_micros() as i64 - epoch.elapsed().as_micros() as i64;
-
-                if delay > 0 {
-                    if let Ok(result) =
-                        time::timeout(Duration::from_micros(delay as u64), tty.read(&mut input))
-                            .await
-                    {
-                        let n = result?;
-                        let key = &input[..n];
-
-                        if keys.quit.as_ref().is_some_and(|k| k == key) {
-                            tty.write_all("\r\n".as_bytes()).await?;
-                            return Ok(false);
-                        }
-
-                        if keys.pause.as_ref().is_some_and(|k| k == key) {
-                            pause_elapsed_time = Some(epoch.elapsed().as_micros() as u64);
-                            break;
-                        }
-
-                        continue;
-                    }
+            return Ok(());
+        };
+
+        self.seek(target).await?;
+        clock.set(target);
+
+        Ok(())
+    }
+
+    fn prev_marker_time(&self, time: Duration) -> Option<Duration> {
+        self.markers
+            .iter()
+            .rev()
+            .filter(|marker| marker.position < self.position)
+            .map(|marker| marker.timestamp.get(self.idle_limited))
+            .find(|&t| t < time)
+    }
+
+    /// Finds the index of the first marker ahead of the current position
+    /// accepted by `predicate`, receiving events as needed.
+    async fn find_marker<F: Fn(&str) -> bool>(&mut self, predicate: F) -> Result<Option<usize>> {
+        let mut i = self.position;
+
+        loop {
+            if i == self.len() && !self.receive().await? {
+                return Ok(None);
+            }
+
+            if let EventData::Marker(label) = &self.event(i).data {
+                if predicate(label) {
+                    return Ok(Some(i));
                 }
+            }
 
-                match data {
-                    EventData::Output(data) => {
-                        tty.write_all(data.as_bytes()).await?;
-                    }
+            i += 1;
+        }
+    }
 
-                    EventData::Resize(cols, rows) if auto_resize => {
-                        tty.resize((*cols as usize, *rows as usize).into()).await?;
-                    }
+    /// Finds the time of a range bound, searching markers from the current
+    /// position.
+    async fn resolve(&mut self, bound: Option<&Bound>) -> Result<Option<Duration>> {
+        match bound {
+            Some(Bound::Time(time)) => Ok(Some(*time)),
 
-                    EventData::Marker(_) => {
-                        if pause_on_markers {
-                            pause_elapsed_time = Some(time.as_micros() as u64);
-                            next_event = events.recv().await.transpose()?;
-                            break;
-                        }
-                    }
+            Some(Bound::Marker(label)) => match self.find_marker(|l| l == label).await? {
+                Some(index) => Ok(Some(self.time_at(index))),
+                None => bail!("marker {label} not found"),
+            },
+
+            None => Ok(None),
+        }
+    }
+
+    /// Moves to the given time, playing all events up to it in the virtual
+    /// terminal only, and repaints the screen with the resulting state.
+    async fn seek(&mut self, time: Duration) -> Result<()> {
+        // keyframes ahead of the received events, after reading the recording
+        // again, are reached by receiving the events anyway
+        let len = self.len();
 
-                    _ => (),
+        let mut index = self
+            .keyframes
+            .iter()
+            .rposition(|keyframe| {
+                keyframe.position <= len && keyframe.timestamp.get(self.idle_limited) <= time
+            })
+            .unwrap();
+
+        let behind = self.time_before(self.position) > time;
+
+        if behind || self.keyframes[index].position > self.position {
+            if self.keyframes[index].position < self.offset {
+                match self.reopen {
+                    Some(reopen) => self.reread(reopen, index).await?,
+
+                    None => {
+                        index = self
+                            .keyframes
+                            .iter()
+                            .position(|keyframe| keyframe.position == self.offset)
+                            .unwrap();
+                    }
                 }
+            }
 
-                next_event = events.recv().await.transpose()?;
+            let keyframe = &self.keyframes[index];
+            self.vt = new_vt(keyframe.size);
+            self.vt.feed_str(&keyframe.dump);
+            self.title = keyframe.title.clone();
+            self.position = keyframe.position;
+        }
+
+        // the event following the ones at the given time isn't waited for, as
+        // in a followed file it may not be written yet
+        while let Some(t) = self.next_time().await? {
+            if t > time {
+                break;
+            }
+
+            self.advance(false).await?;
+
+            if t == time {
+                break;
             }
         }
+
+        self.marker = self
+            .markers
+            .iter()
+            .rev()
+            .find(|marker| marker.position < self.position)
+            .map(|marker| marker.label.clone());
+
+        self.repaint().await
     }
 
-    Ok(true)
+    /// Reads the recording again from the start, skipping the events which
+    /// precede the keyframe with the given index.
+    async fn reread(&mut self, reopen: &Reopen, index: usize) -> Result<()> {
+        let keyframe = &self.keyframes[index];
+        let mut source = emit_session_events(reopen()?)?;
+
+        for _ in 0..keyframe.position {
+            match source.recv().await {
+                Some(Ok(_)) => {}
+                Some(Err(error)) => return Err(error),
+                None => bail!("recording changed while playing it"),
+            }
+        }
+
+        self.source = source;
+        self.complete = false;
+        self.error = None;
+        self.events.clear();
+        self.limited_times.clear();
+        self.offset = keyframe.position;
+        self.base = keyframe.timestamp;
+
+        Ok(())
+    }
+
+    async fn repaint(&mut self) -> Result<()> {
+        if self.auto_resize {
+            self.tty.resize(self.vt.size().into()).await?;
+        }
+
+        // the dump uses 8-bit CSI, which not all terminals accept in UTF-8 mode
+        let dump = self.vt.dump().replace('\u{9b}', "\x1b[");
+        self.tty.write_all(RESET_SEQ.as_bytes()).await?;
+        self.tty.write_all(dump.as_bytes()).await?;
+
+        if let Some(title) = self.title.as_ref().filter(|_| self.status.is_none()) {
+            set_title(self.tty, title).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn change_speed(&mut self, clock: &mut Clock, speed: f64) -> Result<()> {
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        clock.set_speed(speed);
+
+        self.flash(&format!("{speed}x")).await
+    }
+
+    /// Switches between the timeline with the idle time limit applied and the
+    /// original one, staying between the same events.
+    async fn toggle_idle_time_limit(&mut self, clock: &mut Clock) -> Result<()> {
+        let Some(limit) = self.idle_time_limit else {
+            return self.flash("no idle time limit set").await;
+        };
+
+        let elapsed = clock.time().saturating_sub(self.time_before(self.position));
+        self.idle_limited = !self.idle_limited;
+        let time = self.time_before(self.position);
+
+        if self.idle_limited {
+            clock.set(time + elapsed.min(limit));
+            self.flash(&format!("idle time limit {}s", limit.as_secs_f64()))
+                .await
+        } else {
+            clock.set(time + elapsed);
+            self.flash("idle time limit off").await
+        }
+    }
+
+    /// Shows a message in the bottom right corner of the screen, which is
+    /// removed by repainting the screen after `FLASH_DURATION`.
+    async fn flash(&mut self, message: &str) -> Result<()> {
+        let (cols, rows) = self.vt.size();
+        let text = format!(" {message} ");
+        let col = cols.saturating_sub(text.chars().count()) + 1;
+        let seq = format!("\x1b7\x1b[{rows};{col}H\x1b[0;7m{text}\x1b8");
+        self.tty.write_all(seq.as_bytes()).await?;
+        self.flash_until = Some(Instant::now() + FLASH_DURATION);
+
+        Ok(())
+    }
+
+    async fn expire_flash(&mut self) -> Result<()> {
+        if self.flash_until.is_some_and(|t| t <= Instant::now()) {
+            self.flash_until = None;
+            self.repaint().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Shows or hides the status line, which is displayed in the window title.
+    async fn toggle_status(&mut self) -> Result<()> {
+        if self.status.take().is_none() {
+            // rendered on the next update
+            self.status = Some(String::new());
+        } else if let Some(title) = &self.title {
+            set_title(self.tty, title).await?;
+        } else {
+            // restore the title saved when the playback started, and save it again
+            self.tty.write_all(b"\x1b[23;2t\x1b[22;2t").await?;
+        }
+
+        Ok(())
+    }
+
+    async fn update_status(&mut self, clock: &Clock) -> Result<()> {
+        let Some(current) = &self.status else {
+            return Ok(());
+        };
+
+        let mut status = format!(
+            "{} {}",
+            if clock.is_paused() { "⏸" } else { "▶" },
+            format_time(clock.time())
+        );
+
+        if let Some(duration) = self.duration() {
+            status.push_str(&format!(" / {}", format_time(duration)));
+        }
+
+        if let Some(marker) = &self.marker {
+            status.push_str(&format!(" · {marker}"));
+        }
+
+        status.push_str(&format!(" · {}x", clock.speed));
+
+        if *current != status {
+            set_title(self.tty, &status).await?;
+            self.status = Some(status);
+        }
+
+        Ok(())
+    }
+}
+
+fn format_time(time: Duration) -> String {
+    let secs = time.as_secs();
+
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+fn new_vt((cols, rows): (usize, usize)) -> Vt {
+    Vt::builder().size(cols, rows).scrollback_limit(0).build()
+}
+
+/// Translates cursor keys sent in application mode, which the recording may
+/// have enabled, to their normal mode form.
+fn normalize_key(key: &[u8]) -> Vec<u8> {
+    match key {
+        [0x1b, b'O', c @ b'A'..=b'D'] => vec![0x1b, b'[', *c],
+        _ => key.to_vec(),
+    }
+}
+
+async fn set_title<T: RawTty>(tty: &T, title: &str) -> Result<()> {
+    tty.write_all(format!("\x1b]2;{title}\x07").as_bytes())
+        .await?;
+
+    Ok(())
 }
 
 fn emit_session_events(
     recording: asciicast::Asciicast<'static>,
-    speed: f64,
-    idle_time_limit_override: Option<f64>,
 ) -> Result<mpsc::Receiver<Result<Event>>> {
-    let idle_time_limit = idle_time_limit_override
-        .or(recording.header.idle_time_limit)
-        .unwrap_or(f64::MAX);
-
-    let events = asciicast::limit_idle_time(recording.events, idle_time_limit);
-    let events = asciicast::accelerate(events, speed);
+    let events = recording.events;
     let (tx, rx) = mpsc::channel::<Result<Event>>(1024);
 
     tokio::task::spawn_blocking(move || {
@@ -174,3 +973,198 @@ fn emit_session_events(
 
     Ok(rx)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::Cursor;
+
+    use async_trait::async_trait;
+    use avt::Vt;
+    use nix::pty::Winsize;
+    use tokio::io;
+    use tokio::time::Duration;
+
+    use super::{Clock, Player, Reopen};
+    use crate::asciicast::{self, Event, Header, V3Encoder};
+    use crate::tty::{RawTty, TtySize};
+
+    struct SinkTty(RefCell<Vec<u8>>);
+
+    #[async_trait(?Send)]
+    impl RawTty for SinkTty {
+        fn get_size(&self) -> Winsize {
+            TtySize(20, 5).into()
+        }
+
+        async fn read(&self, _buf: &mut [u8]) -> io::Result<usize> {
+            std::future::pending().await
+        }
+
+        async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+
+            Ok(buf.len())
+        }
+    }
+
+    /// Recording with a line of output every 10 seconds, for 200 seconds.
+    fn recording() -> Vec<u8> {
+        let mut enc = V3Encoder::new();
+        let mut data = enc.header(&Header {
+            term_cols: 20,
+            term_rows: 5,
+            ..Default::default()
+        });
+
+        for i in 0..20 {
+            let event = Event::output(Duration::from_secs(i * 10), format!("line {i}\r\n"));
+            data.extend(enc.event(&event));
+        }
+
+        data
+    }
+
+    fn screen_at(secs: u64) -> Vec<String> {
+        let mut vt = Vt::builder().size(20, 5).scrollback_limit(0).build();
+
+        for i in 0..=secs / 10 {
+            vt.feed_str(&format!("line {i}\r\n"));
+        }
+
+        vt.text()
+    }
+
+    #[test]
+    fn clock() {
+        let mut clock = Clock::new(2.0);
+        clock.pause_at(Duration::from_secs(10));
+
+        assert!(clock.is_paused());
+        assert_eq!(clock.time(), Duration::from_secs(10));
+        assert_eq!(clock.until(Duration::from_secs(14)), Duration::from_secs(2));
+        assert_eq!(clock.until(Duration::from_secs(5)), Duration::ZERO);
+
+        clock.set(Duration::from_secs(3));
+
+        assert_eq!(clock.time(), Duration::from_secs(3));
+
+        clock.resume();
+
+        assert!(!clock.is_paused());
+        assert!(clock.time() >= Duration::from_secs(3));
+        assert!(clock.time() < Duration::from_secs(4));
+        assert!(clock.until(Duration::from_secs(5)) <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn clock_speed() {
+        let mut clock = Clock::new(2.0);
+        clock.pause_at(Duration::from_secs(10));
+        clock.set_speed(4.0);
+
+        assert!(clock.is_paused());
+        assert_eq!(clock.time(), Duration::from_secs(10));
+        assert_eq!(clock.until(Duration::from_secs(14)), Duration::from_secs(1));
+
+        clock.resume();
+        clock.set_speed(0.5);
+
+        assert!(clock.time() >= Duration::from_secs(10));
+        assert!(clock.time() < Duration::from_secs(11));
+        assert!(clock.until(Duration::from_secs(12)) > Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn toggle_idle_time_limit() {
+        let mut enc = V3Encoder::new();
+        let mut data = enc.header(&Header::default());
+
+        for (secs, text) in [(0, "foo"), (1, "bar"), (61, "baz")] {
+            data.extend(enc.event(&Event::output(Duration::from_secs(secs), text.to_owned())));
+        }
+
+        let tty = SinkTty(RefCell::new(Vec::new()));
+        let source = super::emit_session_events(asciicast::open(Cursor::new(data)).unwrap());
+        let limit = Some(Duration::from_secs(2));
+        let mut player = Player::new(&tty, source.unwrap(), None, (20, 5), limit, None, false);
+        let mut clock = Clock::new(1.0);
+
+        player.seek(Duration::from_millis(1500)).await.unwrap();
+        clock.pause_at(Duration::from_millis(1500));
+
+        assert_eq!(player.position, 2);
+        assert!(player.idle_limited);
+        assert_eq!(player.time_at(2), Duration::from_secs(3));
+
+        player.toggle_idle_time_limit(&mut clock).await.unwrap();
+
+        assert!(!player.idle_limited);
+        assert_eq!(player.time_at(2), Duration::from_secs(61));
+        assert_eq!(clock.time(), Duration::from_millis(1500));
+
+        // idle for longer than the limit, which is skipped when it's enabled again
+        clock.set(Duration::from_secs(11));
+        player.toggle_idle_time_limit(&mut clock).await.unwrap();
+
+        assert!(player.idle_limited);
+        assert_eq!(clock.time(), Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn seek_to_keyframe() {
+        let data = recording();
+        let open = move || asciicast::open(Cursor::new(data.clone()));
+        let reopen: &Reopen = &open;
+        let tty = SinkTty(RefCell::new(Vec::new()));
+        let source = super::emit_session_events(reopen().unwrap()).unwrap();
+        let mut player = Player::new(&tty, source, Some(reopen), (20, 5), None, None, false);
+
+        player.seek(Duration::from_secs(185)).await.unwrap();
+
+        assert_eq!(player.position, 19);
+        assert_eq!(player.vt.text(), screen_at(185));
+        assert_eq!(player.keyframes.len(), 7);
+        // only the events from the next to last keyframe on are kept
+        assert_eq!(player.offset, player.keyframes[5].position);
+
+        // within the kept events
+        player.seek(Duration::from_secs(175)).await.unwrap();
+
+        assert_eq!(player.position, 18);
+        assert_eq!(player.vt.text(), screen_at(175));
+
+        // before the kept events, reading the recording again
+        player.seek(Duration::from_secs(45)).await.unwrap();
+
+        assert_eq!(player.position, 5);
+        assert_eq!(player.offset, player.keyframes[1].position);
+        assert_eq!(player.vt.text(), screen_at(45));
+        assert_eq!(player.keyframes.len(), 7);
+
+        player.seek(Duration::from_secs(195)).await.unwrap();
+
+        assert_eq!(player.position, 20);
+        assert_eq!(player.vt.text(), screen_at(195));
+        assert_eq!(player.duration(), Some(Duration::from_secs(190)));
+    }
+
+    #[tokio::test]
+    async fn seek_without_reopen() {
+        let data = recording();
+        let tty = SinkTty(RefCell::new(Vec::new()));
+        let source = super::emit_session_events(asciicast::open(Cursor::new(data)).unwrap());
+        let mut player = Player::new(&tty, source.unwrap(), None, (20, 5), None, None, false);
+
+        player.seek(Duration::from_secs(185)).await.unwrap();
+        player.seek(Duration::from_secs(45)).await.unwrap();
+
+        // the oldest kept keyframe
+        let keyframe = &player.keyframes[5];
+        assert_eq!(player.position, keyframe.position);
+        assert_eq!(
+            player.vt.text(),
+            screen_at(keyframe.timestamp.time.as_secs())
+        );
+    }
+}
@@ -1,12 +1,17 @@
 use std::collections::HashMap;
 use std::env;
 use std::ffi::{CString, NulError};
+use std::mem::MaybeUninit;
 use std::os::fd::OwnedFd;
 use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use nix::errno::Errno;
 use nix::pty::{ForkptyResult, Winsize};
 use nix::sys::signal::{self, SigHandler, Signal};
+use nix::sys::stat::{self, Mode};
+use nix::sys::termios::{self, LocalFlags};
 use nix::sys::wait::{self, WaitPidFlag, WaitStatus};
 use nix::unistd::{self, Pid};
 use nix::{libc, pty};
@@ -19,6 +24,30 @@ use crate::fd::FdExt;
 pub struct Pty {
     child: Pid,
     master: AsyncFd<OwnedFd>,
+    started: Instant,
+}
+
+/// How the child process ended and the resources it used.
+#[derive(Debug, Clone, Default)]
+pub struct ExitStatus {
+    pub code: Option<i32>,
+    pub signal: Option<Signal>,
+    pub core_dumped: bool,
+    pub wall_time: Duration,
+    pub user_time: Duration,
+    pub system_time: Duration,
+    /// Peak resident set size in kilobytes.
+    pub max_rss: u64,
+}
+
+/// Process environment of the spawned command.
+#[derive(Default)]
+pub struct ChildOptions {
+    pub cwd: Option<PathBuf>,
+    pub env: HashMap<String, String>,
+    pub unset_env: Vec<String>,
+    pub clean_env: bool,
+    pub umask: Option<u32>,
 }
 
 impl Pty {
@@ -46,14 +75,37 @@ impl Pty {
         unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &winsize) };
     }
 
+    /// Returns true when the line discipline is in canonical mode with echo turned off,
+    /// which is what programs like sudo or ssh do while prompting for a password.
+    pub fn is_input_hidden(&self) -> bool {
+        termios::tcgetattr(self.master.get_ref()).is_ok_and(|termios| {
+            termios.local_flags.contains(LocalFlags::ICANON)
+                && !termios.local_flags.contains(LocalFlags::ECHO)
+        })
+    }
+
     pub fn kill(&self) {
         // Any errors occurred when killing the child are ignored.
         let _ = signal::kill(self.child, Signal::SIGTERM);
     }
 
-    pub async fn wait(&self, options: Option<WaitPidFlag>) -> io::Result<WaitStatus> {
+    pub fn force_kill(&self) {
+        let _ = signal::kill(self.child, Signal::SIGKILL);
+    }
+
+    /// Waits for the child to exit, returning `None` if it's still running
+    /// (with `WNOHANG`).
+    pub async fn wait(&self, options: Option<WaitPidFlag>) -> io::Result<Option<ExitStatus>> {
         let pid = self.child;
-        task::spawn_blocking(move || Ok(wait::waitpid(pid, options)?)).await?
+        let started = self.started;
+
+        task::spawn_blocking(move || {
+            let (wait_status, usage) = wait4(pid, options)?;
+            let wall_time = started.elapsed();
+
+            Ok(ExitStatus::new(wait_status, &usage, wall_time))
+        })
+        .await?
     }
 }
 
@@ -67,7 +119,7 @@ impl Drop for Pty {
 pub fn spawn<S: AsRef<str>>(
     command: &[S],
     winsize: Winsize,
-    extra_env: &HashMap<String, String>,
+    options: &ChildOptions,
 ) -> anyhow::Result<Pty> {
     let result = unsafe { pty::forkpty(Some(&winsize), None) }?;
 
@@ -76,29 +128,106 @@ pub fn spawn<S: AsRef<str>>(
             master.set_nonblocking()?;
             let master = AsyncFd::new(master)?;
 
-            Ok(Pty { child, master })
+            Ok(Pty {
+                child,
+                master,
+                started: Instant::now(),
+            })
         }
 
         ForkptyResult::Child => {
-            handle_child(command, extra_env)?;
+            handle_child(command, options)?;
             unreachable!();
         }
     }
 }
 
-fn handle_child<S: AsRef<str>>(
-    command: &[S],
-    extra_env: &HashMap<String, String>,
-) -> anyhow::Result<()> {
+impl ExitStatus {
+    fn new(wait_status: WaitStatus, usage: &libc::rusage, wall_time: Duration) -> Option<Self> {
+        let (code, signal, core_dumped) = match wait_status {
+            WaitStatus::StillAlive => return None,
+            WaitStatus::Exited(_pid, code) => (Some(code), None, false),
+            WaitStatus::Signaled(_pid, signal, core_dumped) => (None, Some(signal), core_dumped),
+            _ => (Some(1), None, false),
+        };
+
+        // ru_maxrss is in bytes on macOS, in kilobytes elsewhere
+        let max_rss = if cfg!(target_os = "macos") {
+            usage.ru_maxrss as u64 / 1024
+        } else {
+            usage.ru_maxrss as u64
+        };
+
+        Some(Self {
+            code,
+            signal,
+            core_dumped,
+            wall_time,
+            user_time: timeval_to_duration(usage.ru_utime),
+            system_time: timeval_to_duration(usage.ru_stime),
+            max_rss,
+        })
+    }
+
+    /// Exit status as reported by shells, i.e. 128 + signal number for a
+    /// process terminated by a signal.
+    pub fn status(&self) -> i32 {
+        match (self.code, self.signal) {
+            (Some(code), _) => code,
+            (None, Some(signal)) => 128 + signal as i32,
+            (None, None) => 1,
+        }
+    }
+}
+
+fn wait4(pid: Pid, options: Option<WaitPidFlag>) -> nix::Result<(WaitStatus, libc::rusage)> {
+    let mut status = 0;
+    let mut usage = MaybeUninit::<libc::rusage>::zeroed();
+    let options = options.map_or(0, |o| o.bits());
+    let res = unsafe { libc::wait4(pid.as_raw(), &mut status, options, usage.as_mut_ptr()) };
+
+    let wait_status = match Errno::result(res)? {
+        0 => WaitStatus::StillAlive,
+        pid => WaitStatus::from_raw(Pid::from_raw(pid), status)?,
+    };
+
+    Ok((wait_status, unsafe { usage.assume_init() }))
+}
+
+fn timeval_to_duration(tv: libc::timeval) -> Duration {
+    Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+}
+
+fn handle_child<S: AsRef<str>>(command: &[S], options: &ChildOptions) -> anyhow::Result<()> {
     let command = command
         .iter()
         .map(|s| CString::new(s.as_ref()))
         .collect::<Result<Vec<CString>, NulError>>()?;
 
-    for (k, v) in extra_env {
+    if options.clean_env {
+        for (k, _) in env::vars_os() {
+            if k != "TERM" {
+                env::remove_var(k);
+            }
+        }
+    }
+
+    for k in &options.unset_env {
+        env::remove_var(k);
+    }
+
+    for (k, v) in &options.env {
         env::set_var(k, v);
     }
 
+    if let Some(cwd) = &options.cwd {
+        unistd::chdir(cwd)?;
+    }
+
+    if let Some(mask) = options.umask {
+        stat::umask(Mode::from_bits_truncate(mask));
+    }
+
     unsafe { signal::signal(Signal::SIGPIPE, SigHandler::SigDfl) }?;
     unistd::execvp(&command[0], &command)?;
     unsafe { libc::_exit(1) }
@@ -108,11 +237,22 @@ fn handle_child<S: AsRef<str>>(
 mod tests {
     use std::collections::HashMap;
 
-    use super::Pty;
+    use nix::sys::signal::Signal;
+
+    use super::{ChildOptions, Pty};
     use crate::tty::TtySize;
 
     async fn spawn<S: AsRef<str>>(command: &[S], extra_env: &HashMap<String, String>) -> Pty {
-        super::spawn(command, TtySize::default().into(), extra_env).unwrap()
+        let options = ChildOptions {
+            env: extra_env.clone(),
+            ..Default::default()
+        };
+
+        spawn_with_options(command, &options).await
+    }
+
+    async fn spawn_with_options<S: AsRef<str>>(command: &[S], options: &ChildOptions) -> Pty {
+        super::spawn(command, TtySize::default().into(), options).unwrap()
     }
 
     async fn read_output(pty: Pty) -> Vec<String> {
@@ -174,6 +314,101 @@ sys.stdout.write('bar');
         assert_eq!(output, vec!["bar"]);
     }
 
+    #[tokio::test]
+    async fn wait_exit_status() {
+        let pty = spawn(&["sh", "-c", "exit 3"], &HashMap::new()).await;
+        let status = pty.wait(None).await.unwrap().unwrap();
+
+        assert_eq!(status.code, Some(3));
+        assert_eq!(status.signal, None);
+        assert_eq!(status.status(), 3);
+
+        let pty = spawn(&["sh", "-c", "kill -TERM $$"], &HashMap::new()).await;
+        let status = pty.wait(None).await.unwrap().unwrap();
+
+        assert_eq!(status.code, None);
+        assert_eq!(status.signal, Some(Signal::SIGTERM));
+        assert!(!status.core_dumped);
+        assert_eq!(status.status(), 143);
+        assert!(status.max_rss > 0);
+    }
+
+    #[tokio::test]
+    async fn spawn_child_options() {
+        // HOME is inherited from the test process, modifying its environment
+        // here would race with other tests
+        let home = std::env::var("HOME").unwrap_or("none".to_owned());
+
+        let pty = spawn_with_options(
+            &["/bin/sh", "-c", "echo -n ${HOME:-none}"],
+            &ChildOptions::default(),
+        )
+        .await;
+
+        let output = read_output(pty).await.join("");
+
+        assert_eq!(output, home);
+
+        let options = ChildOptions {
+            cwd: Some("/".into()),
+            env: HashMap::from([("ASCIINEMA_TEST_FOO".to_owned(), "bar".to_owned())]),
+            clean_env: true,
+            umask: Some(0o027),
+            ..Default::default()
+        };
+
+        let pty = spawn_with_options(
+            &[
+                "/bin/sh",
+                "-c",
+                "echo -n $(pwd) $(umask) $ASCIINEMA_TEST_FOO ${HOME:-none}",
+            ],
+            &options,
+        )
+        .await;
+
+        let output = read_output(pty).await.join("");
+
+        assert_eq!(output, "/ 0027 bar none");
+
+        let options = ChildOptions {
+            unset_env: vec!["HOME".to_owned()],
+            ..Default::default()
+        };
+
+        let pty = spawn_with_options(&["/bin/sh", "-c", "echo -n ${HOME:-none}"], &options).await;
+
+        let output = read_output(pty).await.join("");
+
+        assert_eq!(output, "none");
+    }
+
+    #[tokio::test]
+    async fn input_hidden() {
+        let pty = spawn(&["cat"], &HashMap::new()).await;
+
+        assert!(!pty.is_input_hidden());
+
+        let pty = spawn(
+            &["sh", "-c", "stty -echo; echo -n ok; sleep 1"],
+            &HashMap::new(),
+        )
+        .await;
+        let mut buf = [0u8; 16];
+        pty.read(&mut buf).await.unwrap();
+
+        assert!(pty.is_input_hidden());
+
+        let pty = spawn(
+            &["sh", "-c", "stty raw -echo; echo -n ok; sleep 1"],
+            &HashMap::new(),
+        )
+        .await;
+        pty.read(&mut buf).await.unwrap();
+
+        assert!(!pty.is_input_hidden());
+    }
+
     #[tokio::test]
     async fn spawn_echo_input() {
         let pty = spawn(&["cat"], &HashMap::new()).await;
@@ -0,0 +1,452 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use nix::pty::Winsize;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use regex::Regex;
+use serde::Deserialize;
+use tokio::io;
+use tokio::sync::mpsc;
+use tokio::time::{self, Instant};
+
+use crate::config;
+use crate::session;
+use crate::tty::{RawTty, TtySize};
+use crate::util::Utf8Decoder;
+
+const DEFAULT_TYPING_DELAY: f64 = 0.08;
+const DEFAULT_TIMEOUT: f64 = 30.0;
+
+static CONTROL_SEQUENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\x1b(\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(\x07|\x1b\\)|[0-Z\\^_`-~])").unwrap()
+});
+
+/// Sequence of steps driving a recording session in place of a human.
+pub struct Script {
+    pub size: Option<TtySize>,
+    typing_delay: Duration,
+    seed: u64,
+    steps: Vec<Step>,
+}
+
+enum Step {
+    Type(String),
+    Press(Vec<u8>),
+    WaitFor(Regex, Duration),
+    Sleep(Duration),
+    Marker(String),
+    Resize(TtySize),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptFile {
+    size: Option<String>,
+    typing_delay: Option<f64>,
+    timeout: Option<f64>,
+    seed: Option<u64>,
+    steps: Vec<StepDef>,
+}
+
+/// Single-key map, e.g. `type: ls` or `press: enter`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepDef {
+    #[serde(rename = "type")]
+    type_: Option<String>,
+    press: Option<String>,
+    wait_for: Option<WaitForDef>,
+    sleep: Option<f64>,
+    marker: Option<String>,
+    resize: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WaitForDef {
+    Pattern(String),
+    Options {
+        pattern: String,
+        timeout: Option<f64>,
+    },
+}
+
+/// Terminal fed with the script's keystrokes, capturing the session output
+/// for the script to wait on.
+pub struct ScriptTty {
+    size: Arc<Mutex<TtySize>>,
+    input_rx: tokio::sync::Mutex<mpsc::Receiver<Vec<u8>>>,
+    output_tx: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+pub struct Driver {
+    script: Script,
+    size: Arc<Mutex<TtySize>>,
+    input_tx: mpsc::Sender<Vec<u8>>,
+    output_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    output: String,
+    decoder: Utf8Decoder,
+    commands_tx: mpsc::Sender<session::Command>,
+}
+
+impl Script {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("can't read script {}", path.to_string_lossy()))?;
+
+        Self::parse(&data).with_context(|| format!("invalid script {}", path.to_string_lossy()))
+    }
+
+    fn parse(data: &str) -> Result<Self> {
+        let file: ScriptFile = serde_yaml_ng::from_str(data)?;
+        let timeout = file.timeout.unwrap_or(DEFAULT_TIMEOUT);
+
+        let steps = file
+            .steps
+            .into_iter()
+            .enumerate()
+            .map(|(i, step)| Step::new(step, timeout).with_context(|| format!("step {}", i + 1)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            size: file.size.as_deref().map(parse_size).transpose()?,
+            typing_delay: seconds(file.typing_delay.unwrap_or(DEFAULT_TYPING_DELAY))?,
+            seed: file.seed.unwrap_or_default(),
+            steps,
+        })
+    }
+
+    /// Prepares the terminal for the session and the driver feeding it.
+    pub fn start(
+        self,
+        size: TtySize,
+        commands_tx: mpsc::Sender<session::Command>,
+    ) -> (ScriptTty, Driver) {
+        let size = Arc::new(Mutex::new(size));
+        let (input_tx, input_rx) = mpsc::channel(1);
+        let (output_tx, output_rx) = mpsc::unbounded_channel();
+
+        let tty = ScriptTty {
+            size: size.clone(),
+            input_rx: tokio::sync::Mutex::new(input_rx),
+            output_tx,
+        };
+
+        let driver = Driver {
+            script: self,
+            size,
+            input_tx,
+            output_rx,
+            output: String::new(),
+            decoder: Utf8Decoder::new(),
+            commands_tx,
+        };
+
+        (tty, driver)
+    }
+}
+
+impl Step {
+    fn new(step: StepDef, timeout: f64) -> Result<Self> {
+        let mut steps = Vec::new();
+
+        if let Some(text) = step.type_ {
+            steps.push(Self::Type(text));
+        }
+
+        if let Some(key) = step.press {
+            steps.push(Self::Press(parse_key(&key)?));
+        }
+
+        if let Some(wait_for) = step.wait_for {
+            let (pattern, t) = match wait_for {
+                WaitForDef::Pattern(pattern) => (pattern, None),
+                WaitForDef::Options { pattern, timeout } => (pattern, timeout),
+            };
+
+            steps.push(Self::WaitFor(
+                Regex::new(&pattern)?,
+                seconds(t.unwrap_or(timeout))?,
+            ));
+        }
+
+        if let Some(secs) = step.sleep {
+            steps.push(Self::Sleep(seconds(secs)?));
+        }
+
+        if let Some(label) = step.marker {
+            steps.push(Self::Marker(label));
+        }
+
+        if let Some(size) = step.resize {
+            steps.push(Self::Resize(parse_size(&size)?));
+        }
+
+        match steps.len() {
+            1 => Ok(steps.pop().unwrap()),
+            0 => bail!("expected one of: type, press, wait_for, sleep, marker, resize"),
+            _ => bail!("expected a single action"),
+        }
+    }
+}
+
+impl Driver {
+    /// Performs the script steps, stopping the session when a step fails.
+    pub async fn run(mut self) -> Result<()> {
+        let result = self.do_run().await;
+
+        if result.is_err() {
+            let _ = self.commands_tx.send(session::Command::Terminate).await;
+        }
+
+        result
+    }
+
+    async fn do_run(&mut self) -> Result<()> {
+        let steps = std::mem::take(&mut self.script.steps);
+        let mut rng = StdRng::seed_from_u64(self.script.seed);
+
+        for (i, step) in steps.into_iter().enumerate() {
+            self.perform(step, &mut rng)
+                .await
+                .with_context(|| format!("script step {} failed", i + 1))?;
+        }
+
+        Ok(())
+    }
+
+    async fn perform(&mut self, step: Step, rng: &mut StdRng) -> Result<()> {
+        match step {
+            Step::Type(text) => {
+                for c in text.chars() {
+                    let mut buf = [0; 4];
+                    self.press(c.encode_utf8(&mut buf).as_bytes(), rng).await?;
+                }
+            }
+
+            Step::Press(key) => {
+                self.press(&key, rng).await?;
+            }
+
+            Step::WaitFor(regex, timeout) => {
+                self.wait_for(&regex, timeout).await?;
+            }
+
+            Step::Sleep(duration) => {
+                time::sleep(duration).await;
+            }
+
+            Step::Marker(label) => {
+                self.send(session::Command::Marker(label)).await?;
+            }
+
+            Step::Resize(size) => {
+                *self.size.lock().unwrap() = size;
+                self.send(session::Command::Resize).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn press(&mut self, key: &[u8], rng: &mut StdRng) -> Result<()> {
+        // vary the delay between keystrokes like a human typist would
+        let delay = self.script.typing_delay.mul_f64(rng.random_range(0.5..1.5));
+        time::sleep(delay).await;
+
+        self.input_tx
+            .send(key.to_vec())
+            .await
+            .map_err(|_| anyhow!("session has ended"))
+    }
+
+    async fn wait_for(&mut self, regex: &Regex, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            // leave the output following the match for the next wait
+            if let Some(end) = find_match(regex, &self.output) {
+                self.output.drain(..end);
+
+                return Ok(());
+            }
+
+            match time::timeout_at(deadline, self.output_rx.recv()).await {
+                Ok(Some(data)) => self.output.push_str(&self.decoder.feed(&data)),
+                Ok(None) => bail!("session has ended"),
+                Err(_) => bail!("timed out waiting for output matching '{regex}'"),
+            }
+        }
+    }
+
+    async fn send(&self, command: session::Command) -> Result<()> {
+        self.commands_tx
+            .send(command)
+            .await
+            .map_err(|_| anyhow!("session has ended"))
+    }
+}
+
+#[async_trait(?Send)]
+impl RawTty for ScriptTty {
+    fn get_size(&self) -> Winsize {
+        (*self.size.lock().unwrap()).into()
+    }
+
+    async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut input_rx = self.input_rx.lock().await;
+
+        match input_rx.recv().await {
+            Some(data) => {
+                let n = data.len().min(buf.len());
+                buf[..n].copy_from_slice(&data[..n]);
+
+                Ok(n)
+            }
+
+            // the script is done, leave it to the command to exit
+            None => std::future::pending().await,
+        }
+    }
+
+    async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        let _ = self.output_tx.send(buf.to_vec());
+
+        Ok(buf.len())
+    }
+}
+
+fn parse_key(key: &str) -> Result<Vec<u8>> {
+    let seq: &[u8] = match key.to_ascii_lowercase().as_str() {
+        "enter" | "return" => b"\r",
+        "tab" => b"\t",
+        "space" => b" ",
+        "backspace" => b"\x7f",
+        "escape" | "esc" => b"\x1b",
+        "up" => b"\x1b[A",
+        "down" => b"\x1b[B",
+        "right" => b"\x1b[C",
+        "left" => b"\x1b[D",
+        "home" => b"\x1b[H",
+        "end" => b"\x1b[F",
+        "delete" => b"\x1b[3~",
+        "pageup" => b"\x1b[5~",
+        "pagedown" => b"\x1b[6~",
+        _ => return config::parse_key(key)?.ok_or(anyhow!("empty key")),
+    };
+
+    Ok(seq.to_vec())
+}
+
+fn parse_size(size: &str) -> Result<TtySize> {
+    let (cols, rows) = size
+        .split_once('x')
+        .ok_or(anyhow!("invalid size '{size}', expected COLSxROWS"))?;
+
+    Ok(TtySize(cols.parse()?, rows.parse()?))
+}
+
+fn seconds(secs: f64) -> Result<Duration> {
+    Duration::try_from_secs_f64(secs).map_err(|_| anyhow!("invalid duration {secs}"))
+}
+
+/// Matches the text with control sequences stripped, returning the end of the
+/// match in the original text.
+fn find_match(regex: &Regex, text: &str) -> Option<usize> {
+    let mut stripped = String::new();
+    // start offsets of the text between control sequences, stripped and original
+    let mut chunks = Vec::new();
+    let mut pos = 0;
+
+    for m in CONTROL_SEQUENCE.find_iter(text) {
+        chunks.push((stripped.len(), pos));
+        stripped.push_str(&text[pos..m.start()]);
+        pos = m.end();
+    }
+
+    chunks.push((stripped.len(), pos));
+    stripped.push_str(&text[pos..]);
+
+    let end = regex.find(&stripped)?.end();
+    let (start, original_start) = chunks.iter().rev().find(|(start, _)| *start <= end)?;
+
+    Some(original_start + end - start)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use regex::Regex;
+
+    use super::{find_match, Script, Step};
+    use crate::tty::TtySize;
+
+    #[test]
+    fn parse() {
+        let script = Script::parse(
+            r#"
+size: 100x30
+typing_delay: 0.05
+timeout: 5
+steps:
+  - wait_for: '\$ $'
+  - type: ls
+  - press: enter
+  - press: ^c
+  - wait_for: { pattern: done, timeout: 1.5 }
+  - sleep: 0.5
+  - marker: listing
+  - resize: 120x40
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(script.size, Some(TtySize(100, 30)));
+        assert_eq!(script.typing_delay, Duration::from_millis(50));
+        assert_eq!(script.steps.len(), 8);
+        assert!(
+            matches!(&script.steps[0], Step::WaitFor(re, t) if re.as_str() == r"\$ $" && *t == Duration::from_secs(5))
+        );
+        assert!(matches!(&script.steps[1], Step::Type(text) if text == "ls"));
+        assert!(matches!(&script.steps[2], Step::Press(key) if key == b"\r"));
+        assert!(matches!(&script.steps[3], Step::Press(key) if key == b"\x03"));
+        assert!(
+            matches!(&script.steps[4], Step::WaitFor(_, t) if *t == Duration::from_millis(1500))
+        );
+        assert!(matches!(&script.steps[5], Step::Sleep(t) if *t == Duration::from_millis(500)));
+        assert!(matches!(&script.steps[6], Step::Marker(label) if label == "listing"));
+        assert!(matches!(&script.steps[7], Step::Resize(TtySize(120, 40))));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Script::parse("steps:\n  - press: hyper\n").is_err());
+        assert!(Script::parse("steps:\n  - wait_for: '('\n").is_err());
+        assert!(Script::parse("steps:\n  - resize: 100\n").is_err());
+        assert!(Script::parse("steps:\n  - dance: now\n").is_err());
+        assert!(Script::parse("steps:\n  - sleep: -1\n").is_err());
+        assert!(Script::parse("steps:\n  - {type: ls, press: enter}\n").is_err());
+        assert!(Script::parse("steps:\n  - {}\n").is_err());
+    }
+
+    #[test]
+    fn control_sequences() {
+        let text = "\x1b[1;32mdone\x1b[0m\x1b]0;title\x07 \x1b7ok\r\n$ ";
+        let find = |pattern| find_match(&Regex::new(pattern).unwrap(), text);
+
+        assert_eq!(find("done ok"), Some(30));
+        // control sequences following the match are skipped too
+        assert_eq!(find("done"), Some(25));
+        assert_eq!(find(r"\$ $"), Some(text.len()));
+        assert_eq!(find("^o"), None);
+        assert_eq!(find("title"), None);
+    }
+}
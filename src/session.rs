@@ -1,25 +1,35 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
 use bytes::{Buf, BytesMut};
 use futures_util::future;
 use futures_util::stream::StreamExt;
-use nix::sys::wait::{WaitPidFlag, WaitStatus};
+use nix::sys::wait::WaitPidFlag;
 use signal_hook::consts::signal::*;
 use signal_hook_tokio::Signals;
 use tokio::io;
-use tokio::sync::mpsc;
-use tokio::time::Instant;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{self, Instant};
 use tracing::error;
 
+use crate::command_markers::CommandMarkers;
 use crate::config::Key;
+use crate::marker_prompt::{self, MarkerPrompt};
 use crate::notifier::Notifier;
-use crate::pty::{self, Pty};
+use crate::pty::{self, ChildOptions, ExitStatus, Pty};
+use crate::redaction::{self, Redactor};
+use crate::title_bell::{Notice, TitleBell};
 use crate::tty::{RawTty, TtySize, TtyTheme};
 use crate::util::Utf8Decoder;
 
 const BUF_SIZE: usize = 128 * 1024;
+const LIMITS_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+/// How often outputs get a chance to write out buffered data.
+pub const OUTPUT_TICK_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Clone)]
 pub enum Event {
@@ -27,7 +37,58 @@ pub enum Event {
     Input(Duration, String),
     Resize(Duration, TtySize),
     Marker(Duration, String),
-    Exit(Duration, i32),
+    Title(Duration, String),
+    Bell(Duration),
+    Exit(Duration, ExitStatus),
+}
+
+impl Event {
+    pub fn time(&self) -> Duration {
+        match self {
+            Event::Output(time, _)
+            | Event::Input(time, _)
+            | Event::Resize(time, _)
+            | Event::Marker(time, _)
+            | Event::Title(time, _)
+            | Event::Bell(time)
+            | Event::Exit(time, _) => *time,
+        }
+    }
+}
+
+pub enum Command {
+    Marker(String),
+    Pause,
+    Resume,
+    Status(oneshot::Sender<Status>),
+    /// Ends the session by killing the command.
+    Terminate,
+    /// Picks up the current terminal size, for terminals resized without SIGWINCH.
+    Resize,
+}
+
+pub struct Status {
+    pub elapsed_time: Duration,
+    pub paused: bool,
+}
+
+#[derive(Default)]
+pub struct Limits {
+    pub max_duration: Option<Duration>,
+    pub max_size: Option<u64>,
+    pub max_idle: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Duration,
+    Size,
+    Idle,
+}
+
+pub struct Outcome {
+    pub status: i32,
+    pub limit: Option<Limit>,
 }
 
 #[derive(Clone)]
@@ -58,6 +119,14 @@ struct Session<N: Notifier> {
     pause_time: Option<Duration>,
     prefix_mode: bool,
     record_input: bool,
+    mark_hidden_input: bool,
+    input_hidden: bool,
+    limits: Limits,
+    limit_reached: Option<Limit>,
+    terminated: bool,
+    recorded_size: u64,
+    last_activity: Instant,
+    marker_prompt: Option<MarkerPrompt>,
     time_offset: Duration,
     tty_size: TtySize,
 }
@@ -66,22 +135,42 @@ struct Session<N: Notifier> {
 pub trait Output: Send {
     async fn event(&mut self, event: Event) -> io::Result<()>;
     async fn flush(&mut self) -> io::Result<()>;
+
+    /// Called every `OUTPUT_TICK_INTERVAL`, also when there are no new events.
+    async fn tick(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run<S: AsRef<str>, T: RawTty + ?Sized, N: Notifier>(
     command: &[S],
-    extra_env: &HashMap<String, String>,
+    child_options: &ChildOptions,
     tty: &mut T,
     record_input: bool,
+    mark_hidden_input: bool,
     outputs: Vec<Box<dyn Output>>,
+    redactor: Option<Redactor>,
+    command_markers: Option<CommandMarkers>,
+    commands_rx: mpsc::Receiver<Command>,
+    limits: Limits,
     keys: KeyBindings,
     notifier: N,
-) -> anyhow::Result<i32> {
+) -> anyhow::Result<Outcome> {
     let epoch = Instant::now();
     let (events_tx, events_rx) = mpsc::channel::<Event>(1024);
     let winsize = tty.get_size();
-    let pty = pty::spawn(command, winsize, extra_env)?;
-    let forwarder = tokio::spawn(forward_events(events_rx, outputs));
+    let pty = pty::spawn(command, winsize, child_options)?;
+    let forwarder = tokio::spawn(forward_events(
+        events_rx,
+        outputs,
+        redactor,
+        command_markers,
+    ));
+
+    let marker_prompt = keys
+        .add_marker_prompt
+        .then(|| MarkerPrompt::new(winsize.into()));
 
     let session = Session {
         epoch,
@@ -93,26 +182,75 @@ pub async fn run<S: AsRef<str>, T: RawTty + ?Sized, N: Notifier>(
         pause_time: None,
         prefix_mode: false,
         record_input,
+        mark_hidden_input,
+        input_hidden: false,
+        limits,
+        limit_reached: None,
+        terminated: false,
+        recorded_size: 0,
+        last_activity: epoch,
+        marker_prompt,
         time_offset: Duration::from_micros(0),
         tty_size: winsize.into(),
     };
 
-    let result = session.run(pty, tty).await;
+    let result = session.run(pty, tty, commands_rx).await;
     let _ = forwarder.await;
 
     result
 }
 
-async fn forward_events(mut events_rx: mpsc::Receiver<Event>, outputs: Vec<Box<dyn Output>>) {
+async fn forward_events(
+    mut events_rx: mpsc::Receiver<Event>,
+    outputs: Vec<Box<dyn Output>>,
+    mut redactor: Option<Redactor>,
+    mut command_markers: Option<CommandMarkers>,
+) {
     let mut outputs = outputs;
+    let mut flush_deadline = None;
+    let mut title_bell = TitleBell::new();
+    let mut tick = time::interval(OUTPUT_TICK_INTERVAL);
+
+    loop {
+        let events = tokio::select! {
+            event = events_rx.recv() => {
+                match (event, redactor.as_mut()) {
+                    (Some(event), Some(redactor)) => redactor.feed(event),
+                    (Some(event), None) => vec![event],
+                    (None, _) => break,
+                }
+            }
 
-    while let Some(event) = events_rx.recv().await {
-        let futs: Vec<_> = outputs
-            .into_iter()
-            .map(|output| forward_event(output, event.clone()))
-            .collect();
+            _ = time::sleep_until(flush_deadline.unwrap_or_else(Instant::now)), if flush_deadline.is_some() => {
+                redactor.as_mut().map(|r| r.flush_partial()).unwrap_or_default()
+            }
+
+            _ = tick.tick() => {
+                outputs = tick_all(outputs).await;
+
+                continue;
+            }
+        };
+
+        let events = add_command_markers(events, command_markers.as_mut());
+
+        for event in add_title_bell_events(events, &mut title_bell) {
+            outputs = forward_event_to_all(outputs, event).await;
+        }
+
+        if redactor.as_ref().is_some_and(|r| r.is_pending()) {
+            flush_deadline.get_or_insert_with(|| Instant::now() + redaction::FLUSH_DELAY);
+        } else {
+            flush_deadline = None;
+        }
+    }
+
+    if let Some(redactor) = redactor.as_mut() {
+        let events = add_command_markers(redactor.flush(), command_markers.as_mut());
 
-        outputs = future::join_all(futs).await.into_iter().flatten().collect();
+        for event in add_title_bell_events(events, &mut title_bell) {
+            outputs = forward_event_to_all(outputs, event).await;
+        }
     }
 
     for mut output in outputs {
@@ -122,6 +260,81 @@ async fn forward_events(mut events_rx: mpsc::Receiver<Event>, outputs: Vec<Box<d
     }
 }
 
+fn add_command_markers(events: Vec<Event>, markers: Option<&mut CommandMarkers>) -> Vec<Event> {
+    let Some(markers) = markers else {
+        return events;
+    };
+
+    let mut result = Vec::with_capacity(events.len());
+
+    for event in events {
+        let labels = match &event {
+            Event::Output(time, text) => markers
+                .feed(text)
+                .into_iter()
+                .map(|label| Event::Marker(*time, label))
+                .collect(),
+
+            _ => Vec::new(),
+        };
+
+        result.push(event);
+        result.extend(labels);
+    }
+
+    result
+}
+
+fn add_title_bell_events(events: Vec<Event>, title_bell: &mut TitleBell) -> Vec<Event> {
+    let mut result = Vec::with_capacity(events.len());
+
+    for event in events {
+        let notices = match &event {
+            Event::Output(time, text) => title_bell
+                .feed(text)
+                .into_iter()
+                .map(|notice| match notice {
+                    Notice::Title(title) => Event::Title(*time, title),
+                    Notice::Bell => Event::Bell(*time),
+                })
+                .collect(),
+
+            _ => Vec::new(),
+        };
+
+        result.push(event);
+        result.extend(notices);
+    }
+
+    result
+}
+
+async fn forward_event_to_all(outputs: Vec<Box<dyn Output>>, event: Event) -> Vec<Box<dyn Output>> {
+    let futs: Vec<_> = outputs
+        .into_iter()
+        .map(|output| forward_event(output, event.clone()))
+        .collect();
+
+    future::join_all(futs).await.into_iter().flatten().collect()
+}
+
+async fn tick_all(outputs: Vec<Box<dyn Output>>) -> Vec<Box<dyn Output>> {
+    let futs: Vec<_> = outputs.into_iter().map(tick_output).collect();
+
+    future::join_all(futs).await.into_iter().flatten().collect()
+}
+
+async fn tick_output(mut output: Box<dyn Output>) -> Option<Box<dyn Output>> {
+    match output.tick().await {
+        Ok(()) => Some(output),
+
+        Err(e) => {
+            error!("output tick handler failed: {e:?}");
+            None
+        }
+    }
+}
+
 async fn forward_event(mut output: Box<dyn Output>, event: Event) -> Option<Box<dyn Output>> {
     match output.event(event).await {
         Ok(()) => Some(output),
@@ -134,14 +347,23 @@ async fn forward_event(mut output: Box<dyn Output>, event: Event) -> Option<Box<
 }
 
 impl<N: Notifier> Session<N> {
-    async fn run<T: RawTty + ?Sized>(mut self, pty: Pty, tty: &mut T) -> anyhow::Result<i32> {
+    async fn run<T: RawTty + ?Sized>(
+        mut self,
+        pty: Pty,
+        tty: &mut T,
+        mut commands_rx: mpsc::Receiver<Command>,
+    ) -> anyhow::Result<Outcome> {
         let mut signals =
             Signals::new([SIGWINCH, SIGINT, SIGTERM, SIGQUIT, SIGHUP, SIGALRM, SIGCHLD])?;
         let mut output_buf = [0u8; BUF_SIZE];
         let mut input_buf = [0u8; BUF_SIZE];
         let mut input = BytesMut::with_capacity(BUF_SIZE);
         let mut output = BytesMut::with_capacity(BUF_SIZE);
-        let mut wait_status = None;
+        let mut exit_status = None;
+        let mut limits_check = time::interval(LIMITS_CHECK_INTERVAL);
+        let has_time_limits = self.limits.max_duration.is_some() || self.limits.max_idle.is_some();
+        let mut kill_deadline: Option<Instant> = None;
+        let mut child_killed = false;
 
         loop {
             tokio::select! {
@@ -151,6 +373,10 @@ impl<N: Notifier> Session<N> {
                     if n > 0 {
                         self.handle_output(&output_buf[..n]).await;
                         output.extend_from_slice(&output_buf[0..n]);
+
+                        if let Some(prompt) = self.marker_prompt.as_mut() {
+                            prompt.output(&output_buf[..n], &mut output);
+                        }
                     } else {
                         break;
                     }
@@ -165,9 +391,13 @@ impl<N: Notifier> Session<N> {
                     let n = result?;
 
                     if n > 0 {
-                        if self.handle_input(&input_buf[..n]).await {
+                        if self.handle_input(&input_buf[..n], pty.is_input_hidden()).await {
                             input.extend_from_slice(&input_buf[..n]);
                         }
+
+                        if let Some(prompt) = self.marker_prompt.as_mut() {
+                            prompt.render(&mut output);
+                        }
                     } else {
                         break;
                     }
@@ -178,6 +408,26 @@ impl<N: Notifier> Session<N> {
                     output.advance(n);
                 }
 
+                Some(command) = commands_rx.recv() => {
+                    if let Command::Resize = command {
+                        let winsize = tty.get_size();
+                        pty.resize(winsize);
+                        self.handle_resize(winsize.into()).await;
+                    } else {
+                        self.handle_command(command).await;
+                    }
+                }
+
+                _ = limits_check.tick(), if has_time_limits && self.limit_reached.is_none() => {
+                    self.check_time_limits().await;
+                }
+
+                _ = time::sleep_until(kill_deadline.unwrap_or_else(Instant::now)), if kill_deadline.is_some() => {
+                    // the child ignored SIGTERM, e.g. an interactive shell
+                    pty.force_kill();
+                    kill_deadline = None;
+                }
+
                 Some(signal) = signals.next() => {
                     match signal {
                         SIGWINCH => {
@@ -191,11 +441,9 @@ impl<N: Notifier> Session<N> {
                         }
 
                         SIGCHLD => {
-                            if let Ok(status) = pty.wait(Some(WaitPidFlag::WNOHANG)).await {
-                                if status != WaitStatus::StillAlive {
-                                    wait_status = Some(status);
-                                    break;
-                                }
+                            if let Ok(Some(status)) = pty.wait(Some(WaitPidFlag::WNOHANG)).await {
+                                exit_status = Some(status);
+                                break;
                             }
                         }
 
@@ -203,6 +451,12 @@ impl<N: Notifier> Session<N> {
                     }
                 }
             }
+
+            if (self.limit_reached.is_some() || self.terminated) && !child_killed {
+                pty.kill();
+                child_killed = true;
+                kill_deadline = Some(Instant::now() + KILL_GRACE_PERIOD);
+            }
         }
 
         while let Ok(n) = pty.read(&mut output_buf).await {
@@ -218,34 +472,46 @@ impl<N: Notifier> Session<N> {
             let _ = tty.write_all(&output).await;
         }
 
-        let wait_status = match wait_status {
-            Some(ws) => ws,
-            None => pty.wait(None).await?,
+        let exit_status = match exit_status {
+            Some(status) => status,
+            None => pty.wait(None).await?.unwrap_or_default(),
         };
 
-        let status = match wait_status {
-            WaitStatus::Exited(_pid, status) => status,
-            WaitStatus::Signaled(_pid, signal, ..) => 128 + signal as i32,
-            _ => 1,
-        };
-
-        self.handle_exit(status).await;
+        let status = exit_status.status();
+        self.handle_exit(exit_status).await;
 
-        Ok(status)
+        Ok(Outcome {
+            status,
+            limit: self.limit_reached,
+        })
     }
 
     async fn handle_output(&mut self, data: &[u8]) {
-        if self.pause_time.is_none() {
+        self.last_activity = Instant::now();
+
+        if self.pause_time.is_none() && self.limit_reached.is_none() {
             let text = self.output_decoder.feed(data);
 
             if !text.is_empty() {
+                self.recorded_size += text.len() as u64;
                 let event = Event::Output(self.elapsed_time(), text);
                 self.send_session_event(event).await;
+                self.check_size_limit().await;
             }
         }
     }
 
-    async fn handle_input(&mut self, data: &[u8]) -> bool {
+    async fn handle_input(&mut self, data: &[u8], hidden: bool) -> bool {
+        self.last_activity = Instant::now();
+
+        if let Some(prompt) = self.marker_prompt.as_mut().filter(|p| p.is_open()) {
+            if let Some(marker_prompt::Action::Submit(label)) = prompt.input(data) {
+                self.add_marker(label).await;
+            }
+
+            return false;
+        }
+
         let prefix_key = self.keys.prefix.as_ref();
         let pause_key = self.keys.pause.as_ref();
         let add_marker_key = self.keys.add_marker.as_ref();
@@ -259,45 +525,149 @@ impl<N: Notifier> Session<N> {
             self.prefix_mode = false;
 
             if pause_key.is_some_and(|key| data == key) {
-                if let Some(pt) = self.pause_time {
-                    self.pause_time = None;
-                    self.time_offset += self.elapsed_time() - pt;
-                    self.notify("Resumed recording").await;
+                if self.pause_time.is_some() {
+                    self.resume().await;
                 } else {
-                    self.pause_time = Some(self.elapsed_time());
-                    self.notify("Paused recording").await;
+                    self.pause().await;
                 }
 
                 return false;
             } else if add_marker_key.is_some_and(|key| data == key) {
-                let event = Event::Marker(self.elapsed_time(), "".to_owned());
-                self.send_session_event(event).await;
-                self.notify("Marker added").await;
+                if let Some(prompt) = self.marker_prompt.as_mut() {
+                    prompt.open();
+                } else {
+                    self.add_marker("".to_owned()).await;
+                }
+
                 return false;
             }
         }
 
-        if self.record_input && self.pause_time.is_none() {
-            let text = self.input_decoder.feed(data);
+        if self.record_input && self.pause_time.is_none() && self.limit_reached.is_none() {
+            self.handle_hidden_input(hidden).await;
 
-            if !text.is_empty() {
-                let event = Event::Input(self.elapsed_time(), text);
-                self.send_session_event(event).await;
+            if !hidden {
+                let text = self.input_decoder.feed(data);
+
+                if !text.is_empty() {
+                    self.recorded_size += text.len() as u64;
+                    let event = Event::Input(self.elapsed_time(), text);
+                    self.send_session_event(event).await;
+                    self.check_size_limit().await;
+                }
             }
         }
 
         true
     }
 
+    async fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::Marker(label) => {
+                self.add_marker(label).await;
+            }
+
+            Command::Pause => {
+                self.pause().await;
+            }
+
+            Command::Resume => {
+                self.resume().await;
+            }
+
+            Command::Terminate => {
+                self.terminated = true;
+            }
+
+            // handled in the run loop, which owns the pty
+            Command::Resize => {}
+
+            Command::Status(reply_tx) => {
+                let _ = reply_tx.send(Status {
+                    elapsed_time: self.elapsed_time(),
+                    paused: self.pause_time.is_some(),
+                });
+            }
+        }
+    }
+
+    async fn pause(&mut self) {
+        if self.pause_time.is_none() {
+            self.pause_time = Some(self.elapsed_time());
+            self.notify("Paused recording").await;
+        }
+    }
+
+    async fn resume(&mut self) {
+        if let Some(pt) = self.pause_time {
+            self.pause_time = None;
+            self.time_offset += self.elapsed_time() - pt;
+            self.notify("Resumed recording").await;
+        }
+    }
+
+    async fn add_marker(&mut self, label: String) {
+        let event = Event::Marker(self.elapsed_time(), label);
+        self.send_session_event(event).await;
+        self.notify("Marker added").await;
+    }
+
+    async fn check_size_limit(&mut self) {
+        if self
+            .limits
+            .max_size
+            .is_some_and(|max| self.recorded_size >= max)
+        {
+            self.stop(Limit::Size).await;
+        }
+    }
+
+    async fn check_time_limits(&mut self) {
+        if self
+            .limits
+            .max_duration
+            .is_some_and(|max| self.elapsed_time() >= max)
+        {
+            self.stop(Limit::Duration).await;
+        } else if self
+            .limits
+            .max_idle
+            .is_some_and(|max| self.last_activity.elapsed() >= max)
+        {
+            self.stop(Limit::Idle).await;
+        }
+    }
+
+    async fn stop(&mut self, limit: Limit) {
+        if self.limit_reached.is_none() {
+            let event = Event::Marker(self.elapsed_time(), format!("stopped: {limit}"));
+            self.send_session_event(event).await;
+            self.limit_reached = Some(limit);
+        }
+    }
+
+    async fn handle_hidden_input(&mut self, hidden: bool) {
+        if hidden && !self.input_hidden && self.mark_hidden_input {
+            let event = Event::Marker(self.elapsed_time(), "input hidden".to_owned());
+            self.send_session_event(event).await;
+        }
+
+        self.input_hidden = hidden;
+    }
+
     async fn handle_resize(&mut self, tty_size: TtySize) {
         if tty_size != self.tty_size {
             let event = Event::Resize(self.elapsed_time(), tty_size);
             self.send_session_event(event).await;
             self.tty_size = tty_size;
         }
+
+        if let Some(prompt) = self.marker_prompt.as_mut() {
+            prompt.resize(tty_size);
+        }
     }
 
-    async fn handle_exit(&mut self, status: i32) {
+    async fn handle_exit(&mut self, status: ExitStatus) {
         let event = Event::Exit(self.elapsed_time(), status);
         self.send_session_event(event).await;
     }
@@ -325,10 +695,21 @@ impl<N: Notifier> Session<N> {
     }
 }
 
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Duration => f.write_str("max duration reached"),
+            Limit::Size => f.write_str("max size reached"),
+            Limit::Idle => f.write_str("idle limit reached"),
+        }
+    }
+}
+
 pub struct KeyBindings {
     pub prefix: Key,
     pub pause: Key,
     pub add_marker: Key,
+    pub add_marker_prompt: bool,
 }
 
 impl Default for KeyBindings {
@@ -337,6 +718,54 @@ impl Default for KeyBindings {
             prefix: None,
             pause: Some(vec![0x1c]), // ^\
             add_marker: None,
+            add_marker_prompt: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use tokio::io;
+    use tokio::sync::mpsc;
+
+    use super::{Event, Output, OUTPUT_TICK_INTERVAL};
+
+    struct TickCounter(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl Output for TickCounter {
+        async fn event(&mut self, _event: Event) -> io::Result<()> {
+            Ok(())
         }
+
+        async fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        async fn tick(&mut self) -> io::Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn idle_outputs_tick() {
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let (events_tx, events_rx) = mpsc::channel(1);
+        let outputs: Vec<Box<dyn Output>> = vec![Box::new(TickCounter(ticks.clone()))];
+        let forwarder = tokio::spawn(super::forward_events(events_rx, outputs, None, None));
+
+        tokio::time::sleep(OUTPUT_TICK_INTERVAL + Duration::from_millis(100)).await;
+        drop(events_tx);
+        forwarder.await.unwrap();
+
+        // the first tick is immediate
+        assert_eq!(ticks.load(Ordering::SeqCst), 2);
     }
 }
@@ -88,6 +88,9 @@ async fn run(
         tokio::select! {
             event = stream_rx.recv() => {
                 match event {
+                    // the live stream protocol carries them in the output already
+                    Some(session::Event::Title(..) | session::Event::Bell(..)) => {}
+
                     Some(event) => {
                         last_event_time = Instant::now();
                         last_event_id = last_event_id.next();
@@ -116,9 +119,11 @@ async fn run(
                             }
 
                             session::Event::Exit(time, status) => {
-                                let _ = broadcast_tx.send(Event::Exit(last_event_id, time, status));
+                                let _ = broadcast_tx.send(Event::Exit(last_event_id, time, status.status()));
                                 stream_time = time;
                             }
+
+                            session::Event::Title(..) | session::Event::Bell(..) => {}
                         }
                     }
 
@@ -0,0 +1,85 @@
+use crate::ansi::{Scanner, Token};
+
+/// Finds window title changes and bells in terminal output.
+///
+/// The title is set with `ESC ] 0 ; <title>` or `ESC ] 2 ; <title>`, terminated
+/// with BEL or ST (`ESC \`). A BEL found outside of a control sequence rings
+/// the bell.
+pub struct TitleBell {
+    scanner: Scanner,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Notice {
+    Title(String),
+    Bell,
+}
+
+const MAX_SEQUENCE_LEN: usize = 1024;
+
+impl TitleBell {
+    pub fn new() -> Self {
+        Self {
+            scanner: Scanner::new(MAX_SEQUENCE_LEN),
+        }
+    }
+
+    pub fn feed(&mut self, text: &str) -> Vec<Notice> {
+        text.chars()
+            .filter_map(|c| match self.scanner.feed(c)? {
+                Token::Char('\x07') => Some(Notice::Bell),
+                Token::Osc(data, _) => parse_title(&data),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn parse_title(data: &str) -> Option<Notice> {
+    match data.split_once(';')? {
+        ("0" | "2", title) => Some(Notice::Title(title.to_owned())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Notice, TitleBell};
+
+    fn feed(chunks: &[&str]) -> Vec<Notice> {
+        let mut title_bell = TitleBell::new();
+
+        chunks
+            .iter()
+            .flat_map(|chunk| title_bell.feed(chunk))
+            .collect()
+    }
+
+    #[test]
+    fn titles_and_bells() {
+        let notices = feed(&[
+            "\x1b]0;vim\x07hello\x07",
+            "\x1b]2;make: buil",
+            "ding\x1b\\\x1b]1;icon\x07\x1b]133;A\x07",
+            "\x1b[1;32m\x1b]2;\x1b\\ding\x07",
+        ]);
+
+        assert_eq!(
+            notices,
+            vec![
+                Notice::Title("vim".to_owned()),
+                Notice::Bell,
+                Notice::Title("make: building".to_owned()),
+                Notice::Title("".to_owned()),
+                Notice::Bell,
+            ]
+        );
+    }
+
+    #[test]
+    fn no_notices() {
+        let notices = feed(&["plain text\r\n\x1b[?1049h\x1b]11;?\x07\x1b]8;;http://a\x1b\\"]);
+
+        assert!(notices.is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+mod emulated;
 mod inspect;
 
 use std::os::fd::AsFd;
@@ -21,6 +22,8 @@ pub use default::DevTty;
 #[cfg(any(target_os = "macos", feature = "macos-tty"))]
 pub use macos::DevTty;
 
+pub use emulated::EmulatedTty;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct TtySize(pub u16, pub u16);
 
@@ -53,6 +56,27 @@ pub trait RawTty {
 
         Ok(())
     }
+
+    /// Asks the terminal to resize its window, using the XTWINOPS sequence.
+    async fn resize(&self, size: TtySize) -> io::Result<()> {
+        let xtwinops_seq = format!("\x1b[8;{};{}t", size.1, size.0);
+        self.write_all(xtwinops_seq.as_bytes()).await?;
+
+        Ok(())
+    }
+}
+
+/// Converts a 0xRRGGBB number to a color.
+pub fn rgb(hex: u32) -> RGB8 {
+    RGB8::new((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+}
+
+impl TtySize {
+    /// Returns the size for a virtual terminal, which can't be zero, while the
+    /// size reported for a terminal may be, e.g. when it's unknown.
+    pub fn vt_size(&self) -> (usize, usize) {
+        (self.0.max(1) as usize, self.1.max(1) as usize)
+    }
 }
 
 impl Default for TtySize {
@@ -10,7 +10,7 @@ use nix::sys::termios::{self, SetArg, Termios};
 use tokio::io::unix::AsyncFd;
 use tokio::io::{self, Interest};
 
-use super::{RawTty, TtySize};
+use super::RawTty;
 
 pub struct DevTty {
     file: AsyncFd<File>,
@@ -30,13 +30,6 @@ impl DevTty {
 
         Ok(Self { file, settings })
     }
-
-    pub async fn resize(&mut self, size: TtySize) -> io::Result<()> {
-        let xtwinops_seq = format!("\x1b[8;{};{}t", size.1, size.0);
-        self.write_all(xtwinops_seq.as_bytes()).await?;
-
-        Ok(())
-    }
 }
 
 impl Drop for DevTty {
@@ -0,0 +1,331 @@
+use std::cell::RefCell;
+
+use async_trait::async_trait;
+use avt::Vt;
+use nix::pty::Winsize;
+use rgb::RGB8;
+use tokio::io;
+use tokio::sync::Notify;
+
+use super::{rgb, RawTty, TtySize, TtyTheme};
+use crate::ansi::{Scanner, Token};
+use crate::util::Utf8Decoder;
+
+/// Longest control sequence considered a query, anything longer is skipped.
+const MAX_SEQ_LEN: usize = 64;
+
+/// Terminal emulated with a virtual terminal, answering the queries a real
+/// terminal would answer, e.g. cursor position or background color.
+pub struct EmulatedTty<T> {
+    inner: T,
+    theme: TtyTheme,
+    state: RefCell<State>,
+    replies: RefCell<Vec<u8>>,
+    replies_ready: Notify,
+}
+
+struct State {
+    vt: Vt,
+    decoder: Utf8Decoder,
+    parser: QueryParser,
+}
+
+#[derive(Debug, PartialEq)]
+enum Query {
+    Status,
+    CursorPosition { private: bool },
+    PrimaryAttributes,
+    SecondaryAttributes,
+    WindowSize,
+    Foreground(String),
+    Background(String),
+    Palette(Vec<usize>, String),
+}
+
+impl<T: RawTty> EmulatedTty<T> {
+    pub fn new(inner: T, theme: Option<TtyTheme>) -> Self {
+        let size: TtySize = inner.get_size().into();
+
+        let state = State {
+            vt: Vt::builder()
+                .size(size.0 as usize, size.1 as usize)
+                .scrollback_limit(0)
+                .build(),
+            decoder: Utf8Decoder::new(),
+            parser: QueryParser::new(),
+        };
+
+        Self {
+            inner,
+            theme: theme.unwrap_or_else(default_theme),
+            state: RefCell::new(state),
+            replies: RefCell::new(Vec::new()),
+            replies_ready: Notify::new(),
+        }
+    }
+
+    fn emulate(&self, data: &[u8]) {
+        let mut state = self.state.borrow_mut();
+        let State {
+            vt,
+            decoder,
+            parser,
+        } = &mut *state;
+
+        let TtySize(cols, rows) = self.inner.get_size().into();
+
+        if vt.size() != (cols as usize, rows as usize) {
+            vt.resize(cols as usize, rows as usize);
+        }
+
+        let mut replies = self.replies.borrow_mut();
+        let len = replies.len();
+
+        let text = decoder.feed(data);
+        let mut fed = 0;
+
+        // answer queries in the terminal state right after them
+        for (i, ch) in text.char_indices() {
+            if let Some(query) = parser.feed(ch) {
+                let end = i + ch.len_utf8();
+                vt.feed_str(&text[fed..end]);
+                fed = end;
+                replies.extend_from_slice(self.reply(&query, vt).as_bytes());
+            }
+        }
+
+        vt.feed_str(&text[fed..]);
+
+        if replies.len() > len {
+            self.replies_ready.notify_one();
+        }
+    }
+
+    fn reply(&self, query: &Query, vt: &Vt) -> String {
+        match query {
+            Query::Status => "\x1b[0n".to_owned(),
+
+            Query::CursorPosition { private } => {
+                let cursor = vt.cursor();
+                let (cols, _) = vt.size();
+                let col = cursor.col.min(cols.saturating_sub(1)) + 1;
+                let prefix = if *private { "?" } else { "" };
+
+                format!("\x1b[{prefix}{};{col}R", cursor.row + 1)
+            }
+
+            // VT220 with ANSI color
+            Query::PrimaryAttributes => "\x1b[?62;22c".to_owned(),
+
+            Query::SecondaryAttributes => "\x1b[>1;10;0c".to_owned(),
+
+            Query::WindowSize => {
+                let (cols, rows) = vt.size();
+
+                format!("\x1b[8;{rows};{cols}t")
+            }
+
+            Query::Foreground(st) => {
+                format!("\x1b]10;{}{st}", format_color(self.theme.fg))
+            }
+
+            Query::Background(st) => {
+                format!("\x1b]11;{}{st}", format_color(self.theme.bg))
+            }
+
+            Query::Palette(indices, st) => indices
+                .iter()
+                .filter_map(|&i| self.theme.palette.get(i).map(|c| (i, c)))
+                .map(|(i, &color)| format!("\x1b]4;{i};{}{st}", format_color(color)))
+                .collect(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<T: RawTty> RawTty for EmulatedTty<T> {
+    fn get_size(&self) -> Winsize {
+        self.inner.get_size()
+    }
+
+    async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            {
+                let mut replies = self.replies.borrow_mut();
+
+                if !replies.is_empty() {
+                    let n = replies.len().min(buf.len());
+                    buf[..n].copy_from_slice(&replies[..n]);
+                    replies.drain(..n);
+
+                    return Ok(n);
+                }
+            }
+
+            tokio::select! {
+                result = self.inner.read(buf) => return result,
+                _ = self.replies_ready.notified() => {}
+            }
+        }
+    }
+
+    async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf).await?;
+        self.emulate(&buf[..n]);
+
+        Ok(n)
+    }
+}
+
+/// Picks queries out of terminal output.
+struct QueryParser(Scanner);
+
+impl QueryParser {
+    fn new() -> Self {
+        Self(Scanner::new(MAX_SEQ_LEN))
+    }
+
+    fn feed(&mut self, ch: char) -> Option<Query> {
+        match self.0.feed(ch)? {
+            Token::Csi(params, final_char) => parse_csi(&params, final_char),
+            Token::Osc(data, st) => parse_osc(&data, st),
+            Token::Char(_) => None,
+        }
+    }
+}
+
+fn parse_csi(params: &str, final_char: char) -> Option<Query> {
+    match (params, final_char) {
+        ("5", 'n') => Some(Query::Status),
+        ("6", 'n') => Some(Query::CursorPosition { private: false }),
+        ("?6", 'n') => Some(Query::CursorPosition { private: true }),
+        ("" | "0", 'c') => Some(Query::PrimaryAttributes),
+        (">" | ">0", 'c') => Some(Query::SecondaryAttributes),
+        ("18", 't') => Some(Query::WindowSize),
+        _ => None,
+    }
+}
+
+fn parse_osc(data: &str, st: &str) -> Option<Query> {
+    match data.split_once(';')? {
+        ("10", "?") => Some(Query::Foreground(st.to_owned())),
+        ("11", "?") => Some(Query::Background(st.to_owned())),
+
+        ("4", specs) => {
+            let parts: Vec<&str> = specs.split(';').collect();
+
+            let indices: Vec<usize> = parts
+                .chunks(2)
+                .filter(|pair| pair.len() == 2 && pair[1] == "?")
+                .filter_map(|pair| pair[0].parse().ok())
+                .collect();
+
+            (!indices.is_empty()).then(|| Query::Palette(indices, st.to_owned()))
+        }
+
+        _ => None,
+    }
+}
+
+fn format_color(color: RGB8) -> String {
+    format!(
+        "rgb:{:02x}{:02x}/{:02x}{:02x}/{:02x}{:02x}",
+        color.r, color.r, color.g, color.g, color.b, color.b
+    )
+}
+
+/// Default theme of asciinema player, used when none is configured.
+fn default_theme() -> TtyTheme {
+    let palette = [
+        0x000000, 0xdd3c69, 0x4ebf22, 0xddaf3c, 0x26b0d7, 0xb954e1, 0x54e1b9, 0xd9d9d9, 0x4d4d4d,
+        0xdd3c69, 0x4ebf22, 0xddaf3c, 0x26b0d7, 0xb954e1, 0x54e1b9, 0xffffff,
+    ];
+
+    TtyTheme {
+        fg: rgb(0xcccccc),
+        bg: rgb(0x121314),
+        palette: palette.into_iter().map(rgb).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use async_trait::async_trait;
+    use nix::pty::Winsize;
+    use tokio::io;
+
+    use super::{EmulatedTty, Query, QueryParser};
+    use crate::tty::{RawTty, TtySize};
+
+    struct SinkTty(RefCell<Vec<u8>>);
+
+    #[async_trait(?Send)]
+    impl RawTty for SinkTty {
+        fn get_size(&self) -> Winsize {
+            TtySize(20, 5).into()
+        }
+
+        async fn read(&self, _buf: &mut [u8]) -> io::Result<usize> {
+            std::future::pending().await
+        }
+
+        async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+
+            Ok(buf.len())
+        }
+    }
+
+    fn parse(input: &str) -> Vec<Query> {
+        let mut parser = QueryParser::new();
+
+        input.chars().filter_map(|ch| parser.feed(ch)).collect()
+    }
+
+    #[test]
+    fn parser() {
+        assert_eq!(
+            parse("a\x1b[6nb\x1b[?6n\x1b[5n\x1b[c\x1b[0c\x1b[>c\x1b[18t"),
+            vec![
+                Query::CursorPosition { private: false },
+                Query::CursorPosition { private: true },
+                Query::Status,
+                Query::PrimaryAttributes,
+                Query::PrimaryAttributes,
+                Query::SecondaryAttributes,
+                Query::WindowSize,
+            ]
+        );
+
+        assert_eq!(
+            parse("\x1b]10;?\x07\x1b]11;?\x1b\\\x1b]4;1;?;3;?\x07"),
+            vec![
+                Query::Foreground("\x07".to_owned()),
+                Query::Background("\x1b\\".to_owned()),
+                Query::Palette(vec![1, 3], "\x07".to_owned()),
+            ]
+        );
+
+        // not queries
+        assert!(parse("\x1b[2J\x1b[1;31m\x1b]0;title\x07\x1b]11;rgb:0/0/0\x07\x1b7").is_empty());
+    }
+
+    #[tokio::test]
+    async fn replies() {
+        let tty = EmulatedTty::new(SinkTty(RefCell::new(Vec::new())), None);
+        tty.write_all(b"hello\r\n\x1b[3Cab\x1b[6n").await.unwrap();
+        tty.write_all(b"\x1b[18t\x1b]11;?\x1b\\").await.unwrap();
+
+        let mut buf = [0u8; 1024];
+        let n = tty.read(&mut buf).await.unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&buf[..n]).unwrap(),
+            "\x1b[2;6R\x1b[8;5;20t\x1b]11;rgb:1212/1313/1414\x1b\\"
+        );
+
+        assert_eq!(tty.inner.0.borrow().len(), 30);
+    }
+}
@@ -22,7 +22,7 @@ use nix::{libc, unistd};
 use tokio::io::unix::AsyncFd;
 use tokio::io::{self, Interest};
 
-use super::{RawTty, TtySize};
+use super::RawTty;
 use crate::fd::FdExt;
 
 const BUF_SIZE: usize = 128 * 1024;
@@ -77,13 +77,6 @@ impl DevTty {
             settings,
         })
     }
-
-    pub async fn resize(&mut self, size: TtySize) -> io::Result<()> {
-        let xtwinops_seq = format!("\x1b[8;{};{}t", size.1, size.0);
-        self.write_all(xtwinops_seq.as_bytes()).await?;
-
-        Ok(())
-    }
 }
 
 impl Drop for DevTty {
@@ -1,7 +1,11 @@
+use std::collections::HashMap;
+use std::ffi::CString;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, bail};
+use nix::libc;
 use reqwest::Url;
 use tempfile::NamedTempFile;
 
@@ -93,6 +97,149 @@ impl Utf8Decoder {
     }
 }
 
+const MAX_STRFTIME_LEN: usize = 65536;
+
+/// Builds the path of a segment file from a pattern containing strftime
+/// placeholders (expanded using the segment start time) and `{n}`, which is
+/// replaced with the segment number.
+pub fn segment_path(pattern: &str, number: usize, time: SystemTime) -> String {
+    strftime(pattern, time).replace("{n}", &number.to_string())
+}
+
+fn strftime(format: &str, time: SystemTime) -> String {
+    let Ok(c_format) = CString::new(format) else {
+        return format.to_owned();
+    };
+
+    let secs = time.duration_since(UNIX_EPOCH).unwrap().as_secs() as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&secs, &mut tm) };
+    let mut buf_len = format.len() * 4 + 256;
+
+    // 0 is returned both for an empty expansion and one not fitting in the buffer
+    while buf_len <= MAX_STRFTIME_LEN {
+        let mut buf = vec![0u8; buf_len];
+
+        let len =
+            unsafe { libc::strftime(buf.as_mut_ptr().cast(), buf.len(), c_format.as_ptr(), &tm) };
+
+        if len > 0 {
+            return String::from_utf8_lossy(&buf[..len]).into_owned();
+        }
+
+        buf_len *= 2;
+    }
+
+    format.to_owned()
+}
+
+/// Renders argv as a shell command line, quoting arguments where needed.
+pub fn shell_join<S: AsRef<str>>(args: &[S]) -> String {
+    args.iter()
+        .map(|arg| shell_quote(arg.as_ref()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-+=%@:,./".contains(c);
+
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Environment variables captured when none are selected explicitly.
+pub const DEFAULT_CAPTURE_ENV: &str = "SHELL";
+
+/// Selects environment variables matching a comma-separated list of name
+/// patterns. Patterns may contain `*` and `?` wildcards, and patterns prefixed
+/// with `!` exclude matching variables from the ones selected by the other
+/// patterns. When only exclusions are given they apply to
+/// `DEFAULT_CAPTURE_ENV`, while no patterns select nothing. Values of
+/// variables matching any of the `redact` patterns are replaced with `***`.
+pub fn filter_env<I>(vars: I, patterns: &str, redact: &str) -> HashMap<String, String>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let patterns = split_patterns(patterns);
+
+    if patterns.is_empty() {
+        return HashMap::new();
+    }
+
+    let (excluded, mut included): (Vec<&str>, Vec<&str>) = patterns
+        .into_iter()
+        .partition(|pattern| pattern.starts_with('!'));
+
+    // exclusions alone never select the whole environment
+    if included.is_empty() {
+        included = split_patterns(DEFAULT_CAPTURE_ENV);
+    }
+
+    let excluded: Vec<&str> = excluded.iter().map(|pattern| &pattern[1..]).collect();
+    let redacted = split_patterns(redact);
+    let matches_any = |patterns: &[&str], name: &str| patterns.iter().any(|p| glob_match(p, name));
+
+    vars.into_iter()
+        .filter(|(name, _)| matches_any(&included, name))
+        .filter(|(name, _)| !matches_any(&excluded, name))
+        .map(|(name, value)| {
+            if matches_any(&redacted, &name) {
+                (name, "***".to_owned())
+            } else {
+                (name, value)
+            }
+        })
+        .collect()
+}
+
+fn split_patterns(patterns: &str) -> Vec<&str> {
+    patterns
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
+/// Matches text against a shell-style pattern, where `*` matches any sequence
+/// of characters and `?` matches a single character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+
+            _ => match backtrack {
+                // let the last star consume one more character
+                Some((star_p, star_t)) => {
+                    backtrack = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 /// Quantizer using error diffusion based on Bresenham algorithm.
 /// It ensures the accumulated error at any point is less than Q/2.
 pub struct Quantizer {
@@ -122,8 +269,93 @@ impl Quantizer {
 
 #[cfg(test)]
 mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
     use super::{Quantizer, Utf8Decoder};
 
+    #[test]
+    fn segment_path() {
+        let time = UNIX_EPOCH + Duration::from_secs(86400 * 365);
+
+        assert_eq!(
+            super::segment_path("/tmp/session-%Y-{n}.cast", 3, time),
+            "/tmp/session-1971-3.cast"
+        );
+
+        assert_eq!(super::segment_path("demo.cast", 1, time), "demo.cast");
+
+        assert_eq!(super::segment_path("%", 1, time), "%");
+
+        let path = super::segment_path(&"%c".repeat(100), 1, time);
+        assert!(path.len() > 2000 && !path.contains('%'));
+    }
+
+    #[test]
+    fn glob_match() {
+        assert!(super::glob_match("SHELL", "SHELL"));
+        assert!(!super::glob_match("SHELL", "SHELLS"));
+        assert!(super::glob_match("LC_*", "LC_ALL"));
+        assert!(super::glob_match("LC_*", "LC_"));
+        assert!(!super::glob_match("LC_*", "LANG"));
+        assert!(super::glob_match("*TOKEN*", "GITHUB_TOKEN"));
+        assert!(super::glob_match("*TOKEN*", "TOKENS_DIR"));
+        assert!(super::glob_match("TER?", "TERM"));
+        assert!(!super::glob_match("TER?", "TER"));
+        assert!(super::glob_match("*_*_KEY", "AWS_SECRET_ACCESS_KEY"));
+        assert!(super::glob_match("*", ""));
+    }
+
+    #[test]
+    fn filter_env() {
+        let vars = || {
+            [
+                ("SHELL", "/bin/zsh"),
+                ("LC_ALL", "C"),
+                ("GIT_AUTHOR_NAME", "Foo"),
+                ("GIT_TOKEN", "s3cr3t"),
+                ("HOME", "/home/foo"),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        };
+
+        let mut env: Vec<_> =
+            super::filter_env(vars(), "SHELL,LC_*,GIT_*,!*TOKEN*", "GIT_AUTHOR_*")
+                .into_iter()
+                .collect();
+
+        env.sort();
+
+        assert_eq!(
+            env,
+            vec![
+                ("GIT_AUTHOR_NAME".to_owned(), "***".to_owned()),
+                ("LC_ALL".to_owned(), "C".to_owned()),
+                ("SHELL".to_owned(), "/bin/zsh".to_owned()),
+            ]
+        );
+
+        let env = super::filter_env(vars(), "!*TOKEN*, !LC_*", "");
+
+        assert_eq!(env.len(), 1);
+        assert_eq!(env["SHELL"], "/bin/zsh");
+        assert!(super::filter_env(vars(), "!SHELL", "").is_empty());
+
+        assert!(super::filter_env(vars(), "USER", "").is_empty());
+        assert!(super::filter_env(vars(), "", "").is_empty());
+        assert!(super::filter_env(vars(), " , ", "").is_empty());
+    }
+
+    #[test]
+    fn shell_join() {
+        assert_eq!(super::shell_join(&["ls", "-la", "/tmp"]), "ls -la /tmp");
+
+        assert_eq!(
+            super::shell_join(&["prog", "arg 1", "", "it's", "$HOME", "a;b"]),
+            r#"prog 'arg 1' '' 'it'\''s' '$HOME' 'a;b'"#
+        );
+    }
+
     #[test]
     fn utf8_decoder() {
         let mut decoder = Utf8Decoder::new();
@@ -145,6 +377,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::zero_prefixed_literal)]
     fn quantizer() {
         let mut quantizer = Quantizer::new(1_000);
 
@@ -110,6 +110,20 @@ assert_file_contains() {
     fi
 }
 
+assert_file_not_contains() {
+    local unexpected=$1
+    local file=$2
+    local test_name=$3
+    
+    ((TESTS_RUN++))
+    if ! grep -q "$unexpected" "$file"; then
+        log_success "$test_name - file doesn't contain: $unexpected"
+    else
+        log_error "$test_name - file contains: $unexpected"
+        return 1
+    fi
+}
+
 # SETUP
 setup() {
     log_info "Setting up test environment..."
@@ -251,6 +265,7 @@ test_record() {
     if "$ASCIINEMA_BIN" record --headless --command 'exit 42' --return "$file6"; then rc=0; else rc=$?; fi
     assert_exit_code 42 "$rc" "record return flag with failure"
     assert_file_not_empty "$file6" "record failure"
+    assert_file_contains '"x", "42"\]$' "$file6" "record failure exit event"
     
     # Test append mode
     local file7="$TMP_DATA_DIR/record_append.cast"
@@ -266,6 +281,90 @@ test_record() {
     if "$ASCIINEMA_BIN" record --headless --command 'bash -c "echo start; sleep 2; echo end"' --idle-time-limit 1 --return "$file8"; then rc=0; else rc=$?; fi
     assert_exit_code 0 "$rc" "record idle time limit"
     assert_file_not_empty "$file8" "record idle time limit"
+
+    # Test captured env patterns and redaction
+    local file12="$TMP_DATA_DIR/record_env.cast"
+    local config="${ASCIINEMA_CONFIG_HOME}/config.toml"
+    cp "$config" "$config.orig"
+    printf '[session]\ncapture_env_redact = "*_SECRET"\n' >> "$config"
+    if LC_TEST=pl APP_SECRET=s3cr3t APP_TOKEN=t0k3n "$ASCIINEMA_BIN" record --headless \
+        --capture-env 'LC_TEST,APP_*,!*TOKEN*' --command 'true' "$file12"; then rc=0; else rc=$?; fi
+    mv "$config.orig" "$config"
+    assert_exit_code 0 "$rc" "record env patterns"
+    assert_file_contains '"LC_TEST":"pl"' "$file12" "record env glob"
+    assert_file_contains '"APP_SECRET":"\*\*\*"' "$file12" "record env redaction"
+    assert_file_not_contains 'APP_TOKEN' "$file12" "record env exclusion"
+
+    # Test session hooks
+    local file13="$TMP_DATA_DIR/record_hooks.cast"
+    local hooks_log="$TMP_DATA_DIR/record_hooks.log"
+    cp "$config" "$config.orig"
+    printf '[session]\npre_hook = "echo pre $ASCIINEMA_OUTPUT_PATH >> %s"\npost_hook = "echo post $ASCIINEMA_EXIT_STATUS $ASCIINEMA_SESSION >> %s"\n' \
+        "$hooks_log" "$hooks_log" >> "$config"
+    if "$ASCIINEMA_BIN" record --headless --command 'exit 3' "$file13"; then rc=0; else rc=$?; fi
+    mv "$config.orig" "$config"
+    assert_exit_code 0 "$rc" "record hooks"
+    assert_file_contains "pre $file13" "$hooks_log" "record pre hook"
+    assert_file_contains "post 3 [0-9a-f]" "$hooks_log" "record post hook"
+
+    # Test config profiles
+    local file14="$TMP_DATA_DIR/record_profile.cast"
+    cp "$config" "$config.orig"
+    printf '[profiles.demo.session]\nidle_time_limit = 1.5\nwindow_size = "100x30"\n' >> "$config"
+    if "$ASCIINEMA_BIN" record --profile demo --headless --command 'true' "$file14"; then rc=0; else rc=$?; fi
+    local output
+    output=$("$ASCIINEMA_BIN" --profile nope record --headless --command 'true' "$file14.nope" 2>&1) || true
+    mv "$config.orig" "$config"
+    assert_exit_code 0 "$rc" "record profile"
+    assert_file_contains '"cols":100,"rows":30' "$file14" "record profile window size"
+    assert_file_contains '"idle_time_limit":1.5' "$file14" "record profile idle time limit"
+    assert_output_contains "profile nope not found" "$output" "record unknown profile"
+
+    # Test upload failure keeps the recording, with the post hook run after the upload
+    local file15="$TMP_DATA_DIR/record_upload.cast"
+    cp "$config" "$config.orig"
+    printf '[session]\npost_hook = "echo post hook ${ASCIINEMA_UPLOAD_URL:-none}"\n' >> "$config"
+    output=$(ASCIINEMA_SERVER_URL=http://127.0.0.1:9 "$ASCIINEMA_BIN" record --headless --upload-and-delete --command 'echo hi' "$file15" 2>&1) && rc=0 || rc=$?
+    mv "$config.orig" "$config"
+    assert_exit_code 0 "$rc" "record upload failure"
+    assert_output_contains "Upload failed" "$output" "record upload failure warning"
+    assert_output_contains "Upload failed.*post hook none" "$(echo $output)" "record post hook after upload"
+    assert_file_contains 'hi' "$file15" "record upload failure keeps file"
+
+    # Test scripted input
+    local script="$TMP_DATA_DIR/record_script.yaml"
+    local file9="$TMP_DATA_DIR/record_script.cast"
+    printf '%s\n' 'size: 100x30' 'typing_delay: 0.01' 'steps:' \
+        '  - type: hello' '  - press: enter' '  - wait_for: hello\r\nhello' \
+        '  - marker: greeted' '  - resize: 120x40' '  - press: ^d' > "$script"
+    if "$ASCIINEMA_BIN" record --script "$script" --capture-input --command cat --return "$file9"; then rc=0; else rc=$?; fi
+    assert_exit_code 0 "$rc" "record script"
+    assert_file_contains '"cols":100,"rows":30' "$file9" "record script size"
+    assert_file_contains '"i", "h"' "$file9" "record script input event"
+    assert_file_contains '"m", "greeted"' "$file9" "record script marker"
+    assert_file_contains '"r", "120x40"' "$file9" "record script resize"
+
+    local file10="$TMP_DATA_DIR/record_script_fail.cast"
+    printf '%s\n' 'steps:' '  - wait_for: { pattern: never, timeout: 0.5 }' > "$script"
+    if "$ASCIINEMA_BIN" record --script "$script" --command 'sleep 10' "$file10"; then rc=0; else rc=$?; fi
+    assert_exit_code 1 "$rc" "record script failure"
+
+    # Test title and bell events
+    local file16="$TMP_DATA_DIR/record_title_bell.cast"
+    if "$ASCIINEMA_BIN" record --headless --command "printf '\\033]2;Build\\007done\\007'" --return "$file16"; then rc=0; else rc=$?; fi
+    assert_exit_code 0 "$rc" "record title and bell"
+    assert_file_contains '"t", "Build"' "$file16" "record title event"
+    assert_file_contains '"b", ""' "$file16" "record bell event"
+
+    # Test exit details
+    local file17="$TMP_DATA_DIR/record_exit_details.cast"
+    cp "$config" "$config.orig"
+    printf '[session]\nexit_details = true\n' >> "$config"
+    if "$ASCIINEMA_BIN" record --headless --command 'exit 42' "$file17"; then rc=0; else rc=$?; fi
+    mv "$config.orig" "$config"
+    assert_exit_code 0 "$rc" "record exit details"
+    assert_file_contains '"e", "{\\"code\\":42' "$file17" "record exit details event"
+    assert_file_contains '"x", "42"\]$' "$file17" "record exit details exit event"
 }
 
 test_play() {
@@ -290,6 +389,28 @@ test_play() {
     assert_exit_code 0 "$rc" "play from stdin"
     assert_output_contains "Replaying session from -" "$output" "play stdin start message"
     assert_output_contains "Playback ended" "$output" "play stdin end message"
+
+    # Test playback of a range between markers
+    local ranged="$TMP_DATA_DIR/play_range.cast"
+    printf '%s\n' '{"version": 2, "width": 80, "height": 24}' '[1.0, "o", "before "]' \
+        '[2.0, "m", "start"]' '[3.0, "o", "inside "]' '[4.0, "m", "end"]' '[5.0, "o", "after "]' > "$ranged"
+    if output=$(timeout --foreground 10s "$ASCIINEMA_BIN" play --speed 1000 --from-marker start --to-marker end "$ranged" 2>&1); then rc=0; else rc=$?; fi
+    assert_exit_code 0 "$rc" "play marker range"
+    assert_output_contains "Playback ended" "$output" "play marker range end message"
+
+    if output=$(timeout --foreground 10s "$ASCIINEMA_BIN" play --from-marker nope "$ranged" 2>&1); then rc=0; else rc=$?; fi
+    assert_exit_code 1 "$rc" "play unknown marker"
+    assert_output_contains "marker nope not found" "$output" "play unknown marker message"
+
+    # Test following a file which gets its exit event while being played,
+    # starting at its current end instead of playing the long recorded part
+    local followed="$TMP_DATA_DIR/play_follow.cast"
+    printf '%s\n' '{"version": 3, "term": {"cols": 80, "rows": 24}}' '[0.1, "o", "hello "]' '[600, "o", "there "]' > "$followed"
+    (sleep 1; printf '%s\n' '[0.1, "o", "world"]' '[0.1, "x", "0"]' >> "$followed") &
+    if output=$(timeout --foreground 10s "$ASCIINEMA_BIN" play --follow "$followed" 2>&1); then rc=0; else rc=$?; fi
+    wait
+    assert_exit_code 0 "$rc" "play follow"
+    assert_output_contains "Playback ended" "$output" "play follow end message"
 }
 
 test_stream() {
@@ -365,6 +486,16 @@ test_session() {
     assert_exit_code 0 "$rc" "session append"
     assert_file_contains 'first session' "$file5" "session append first content"
     assert_file_contains 'second session' "$file5" "session append second content"
+    
+    # Test multiple file outputs
+    local file6="$TMP_DATA_DIR/session_multi.cast"
+    local file7="$TMP_DATA_DIR/session_multi.txt"
+    local file8="$TMP_DATA_DIR/session_multi.log"
+    if "$ASCIINEMA_BIN" session --headless -o "$file6" -o "$file7" -o "$file8:raw" --command 'echo "multi session"' --return; then rc=0; else rc=$?; fi
+    assert_exit_code 0 "$rc" "session multiple outputs"
+    assert_file_contains '"o", "multi session' "$file6" "session multiple outputs asciicast content"
+    assert_file_contains 'multi session' "$file7" "session multiple outputs txt content"
+    assert_file_contains 'multi session' "$file8" "session multiple outputs raw content"
 }
 
 test_cat() {
@@ -436,6 +567,48 @@ test_convert() {
     assert_file_contains '"version":3' "$file5" "convert overwrite content"
 }
 
+test_repair() {
+    log_info "Testing repair command..."
+    
+    # Test repairing a truncated recording
+    local file1="$TMP_DATA_DIR/repair_truncated.cast"
+    local rc
+    printf '{"version": 3, "term": {"cols": 80, "rows": 24}}\n[0.5, "o", "before crash\\r\\n"]\n[0.5, "o", "half wri' > "$file1"
+    if "$ASCIINEMA_BIN" convert "$file1" - > /dev/null 2>&1; then rc=0; else rc=$?; fi
+    assert_exit_code 1 "$rc" "convert truncated recording"
+    if "$ASCIINEMA_BIN" repair "$file1"; then rc=0; else rc=$?; fi
+    assert_exit_code 0 "$rc" "repair truncated recording"
+    assert_file_contains 'before crash' "$file1" "repair keeps complete events"
+    assert_file_contains '"x", "1"' "$file1" "repair adds exit event"
+    if "$ASCIINEMA_BIN" convert "$file1" - > /dev/null; then rc=0; else rc=$?; fi
+    assert_exit_code 0 "$rc" "convert repaired recording"
+    
+    # Test saving to a new file
+    local file2="$TMP_DATA_DIR/repair_output.cast"
+    if "$ASCIINEMA_BIN" repair "$FIXTURES/minimal-v2.cast" --output "$file2"; then rc=0; else rc=$?; fi
+    assert_exit_code 0 "$rc" "repair to new file"
+    assert_file_contains '"version":2' "$file2" "repair keeps version"
+}
+
+test_ctl() {
+    log_info "Testing ctl command..."
+
+    # Test controlling a session from within
+    local file1="$TMP_DATA_DIR/ctl_session.cast"
+    local rc
+    if "$ASCIINEMA_BIN" record --headless --command "'$ASCIINEMA_BIN' ctl marker checkpoint; '$ASCIINEMA_BIN' ctl title 'New title'; '$ASCIINEMA_BIN' ctl status" --return "$file1"; then rc=0; else rc=$?; fi
+    assert_exit_code 0 "$rc" "ctl in session"
+    assert_file_contains '"m", "checkpoint"' "$file1" "ctl marker"
+    assert_file_contains '"title":"New title"' "$file1" "ctl title"
+    assert_file_contains 'paused: no' "$file1" "ctl status"
+
+    # Test outside of a session
+    local output
+    if output=$(env -u ASCIINEMA_CTL_SOCKET "$ASCIINEMA_BIN" ctl status 2>&1); then rc=0; else rc=$?; fi
+    assert_exit_code 1 "$rc" "ctl outside session"
+    assert_output_contains "not inside an asciinema session" "$output" "ctl outside session error"
+}
+
 # MAIN EXECUTION
 
 # Setup always runs
@@ -458,6 +631,8 @@ run_test "stream" test_stream
 run_test "session" test_session
 run_test "cat" test_cat
 run_test "convert" test_convert
+run_test "repair" test_repair
+run_test "ctl" test_ctl
 
 # Final summary
 echo